@@ -2,8 +2,10 @@ from dslib import Context, Message, Node
 
 
 class GroupMember(Node):
-    def __init__(self, node_id: str):
+    def __init__(self, node_id: str, passive: bool = False):
         self._id = node_id
+        # in passive mode MEMBERS should be sent on each change of the members list (see readme)
+        self._passive = passive
 
     def on_local_message(self, msg: Message, ctx: Context):
         if msg.type == 'JOIN':
@@ -21,6 +23,8 @@ class GroupMember(Node):
         elif msg.type == 'GET_MEMBERS':
             # Get a list of group members
             # - return the list of all known alive nodes in MEMBERS message
+            # - in passive mode the same message should also be sent without request
+            #   on each change of the list
             ctx.send_local(Message('MEMBERS', {'members': [self._id]}))
 
     def on_message(self, msg: Message, sender: str, ctx: Context):
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet};
 use std::env;
+use std::fs::{self, File};
 use std::io::Write;
 
 use assertables::{assume, assume_eq};
@@ -11,10 +12,14 @@ use rand_pcg::Pcg64;
 use serde::{Deserialize, Serialize};
 use sugars::{rc, refcell};
 
+use dslib::node::LocalEventType;
 use dslib::pynode::{JsonMessage, PyNodeFactory};
 use dslib::system::System;
 use dslib::test::{TestResult, TestSuite};
 
+mod swim;
+use swim::SwimNode;
+
 // UTILS -------------------------------------------------------------------------------------------
 
 #[derive(Serialize)]
@@ -38,6 +43,14 @@ struct TestConfig<'a> {
     node_factory: &'a PyNodeFactory,
     node_count: u32,
     seed: u64,
+    churn_rate: f64,
+    flap_period: f64,
+    clock_drift: f64,
+    csv_dir: Option<String>,
+    passive: bool,
+    reference_ratio: f64,
+    compare_reference: bool,
+    debug: bool,
 }
 
 fn init_logger(level: LevelFilter) {
@@ -47,15 +60,34 @@ fn init_logger(level: LevelFilter) {
         .init();
 }
 
+// Nodes running the reference implementation are spread evenly over node ids
+fn is_reference_node(node_id: &str, config: &TestConfig) -> bool {
+    let n: f64 = node_id.parse().unwrap();
+    (n * 0.618034).fract() < config.reference_ratio
+}
+
+fn add_node(node_id: &str, sys: &mut System<JsonMessage>, config: &TestConfig) {
+    if is_reference_node(node_id, config) {
+        let node = SwimNode::new(node_id, config.seed, config.passive);
+        sys.add_node(rc!(refcell!(node)));
+    } else if config.passive {
+        // passive mode is passed only if enabled to support solutions without its support
+        let node = config
+            .node_factory
+            .build(node_id, (node_id, true), config.seed);
+        sys.add_node(rc!(refcell!(node)));
+    } else {
+        let node = config.node_factory.build(node_id, (node_id,), config.seed);
+        sys.add_node(rc!(refcell!(node)));
+    }
+}
+
 fn build_system(config: &TestConfig) -> System<JsonMessage> {
     let mut sys = System::with_seed(config.seed);
     sys.set_delays(0.01, 0.1);
     for n in 0..config.node_count {
         let node_id = format!("{}", n);
-        let node = config
-            .node_factory
-            .build(&node_id, (&node_id,), config.seed);
-        sys.add_node(rc!(refcell!(node)));
+        add_node(&node_id, &mut sys, config);
         let clock_skew = sys.gen_range(0.0..10.0);
         sys.set_clock_skew(&node_id, clock_skew);
     }
@@ -63,11 +95,26 @@ fn build_system(config: &TestConfig) -> System<JsonMessage> {
 }
 
 fn recover_node(node_id: &str, sys: &mut System<JsonMessage>, config: &TestConfig) {
-    let node = config.node_factory.build(node_id, (node_id,), config.seed);
-    sys.add_node(rc!(refcell!(node)));
+    add_node(node_id, sys, config);
 }
 
 fn step_until_stabilized(sys: &mut System<JsonMessage>, group: HashSet<String>) -> TestResult {
+    step_until_stabilized_impl(sys, group, true)
+}
+
+// Same as step_until_stabilized, but members lists should not contain duplicate entries (e.g. for restarted node)
+fn step_until_stabilized_without_duplicates(
+    sys: &mut System<JsonMessage>,
+    group: HashSet<String>,
+) -> TestResult {
+    step_until_stabilized_impl(sys, group, false)
+}
+
+fn step_until_stabilized_impl(
+    sys: &mut System<JsonMessage>,
+    group: HashSet<String>,
+    allow_duplicates: bool,
+) -> TestResult {
     let max_time = sys.time() + 300.; // timeout is 5 minutes
     let mut stabilized = HashSet::new();
     let mut memberlists = HashMap::new();
@@ -86,11 +133,13 @@ fn step_until_stabilized(sys: &mut System<JsonMessage>, group: HashSet<String>)
                 format!("Members list is not returned by {}", &node)
             )?;
             let msgs = res.unwrap();
-            let msg = msgs.first().unwrap();
+            // in passive mode the node can also send MEMBERS without request,
+            // the last message contains the most recent members list
+            let msg = msgs.last().unwrap();
             assume!(msg.tip == "MEMBERS", "Wrong message type")?;
             let data: MembersMessage = serde_json::from_str(&msg.data).unwrap();
             let members: HashSet<String> = data.members.clone().into_iter().collect();
-            if members.eq(&group) {
+            if members.eq(&group) && (allow_duplicates || members.len() == data.members.len()) {
                 stabilized.insert(node.clone());
             }
             memberlists.insert(node.clone(), data.members);
@@ -117,6 +166,691 @@ fn step_until_stabilized(sys: &mut System<JsonMessage>, group: HashSet<String>)
     Ok(true)
 }
 
+fn sample_members(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+) -> Result<HashMap<String, HashSet<String>>, String> {
+    sample_members_with_drift(sys, None, nodes)
+}
+
+// Clocks keep drifting while waiting for responses if given
+fn sample_members_with_drift(
+    sys: &mut System<JsonMessage>,
+    mut clocks: Option<&mut ClockDrift>,
+    nodes: &[String],
+) -> Result<HashMap<String, HashSet<String>>, String> {
+    // request members lists from all nodes at once to get a consistent snapshot
+    for node in nodes.iter() {
+        sys.send_local(
+            JsonMessage::from("GET_MEMBERS", &GetMembersMessage {}),
+            node,
+        );
+    }
+    let mut views = HashMap::new();
+    for node in nodes.iter() {
+        let res = match clocks.as_deref_mut() {
+            Some(clocks) => clocks.step_until_local_message(sys, node, 10.),
+            None => sys.step_until_local_message_with_timeout(node, 10.).ok(),
+        };
+        assume!(
+            res.is_some(),
+            format!("Members list is not returned by {}", node)
+        )?;
+        let msgs = res.unwrap();
+        let msg = msgs.last().unwrap();
+        assume!(msg.tip == "MEMBERS", "Wrong message type")?;
+        let data: MembersMessage = serde_json::from_str(&msg.data).unwrap();
+        views.insert(node.clone(), data.members.into_iter().collect());
+    }
+    Ok(views)
+}
+
+// Schedule of link faults: flapping links are periodically disabled and enabled again,
+// broken links stay disabled all the time.
+struct LinkFaults {
+    flapping: Vec<(String, String)>,
+    broken: Vec<(String, String)>,
+    period: f64,
+}
+
+fn apply_link_faults(sys: &mut System<JsonMessage>, faults: &LinkFaults, links_up: bool) {
+    sys.reset_network();
+    for (from, to) in faults.broken.iter() {
+        sys.disable_link(from, to);
+    }
+    if !links_up {
+        for (from, to) in faults.flapping.iter() {
+            sys.disable_link(from, to);
+        }
+    }
+}
+
+const FLAP_SAMPLE_INTERVAL: f64 = 1.;
+const FLAP_DURATION: f64 = 120.;
+// Members lists can change a few times while nodes adapt to the faults,
+// but the number of changes should not grow with the number of link state changes
+// (with default flap period this allows 10 changes per node)
+const FLAP_BASE_VIEW_CHANGES: usize = 4;
+const FLAP_VIEW_CHANGES_PER_TOGGLE: f64 = 0.05;
+
+impl LinkFaults {
+    fn max_view_changes(&self, duration: f64) -> usize {
+        let toggles = if self.flapping.is_empty() {
+            0.
+        } else {
+            duration / self.period
+        };
+        FLAP_BASE_VIEW_CHANGES + (toggles * FLAP_VIEW_CHANGES_PER_TOGGLE) as usize
+    }
+}
+
+// Runs the system under link faults and counts members list changes on each node using members timeline.
+// In passive mode all changes are observed, otherwise members lists are polled each FLAP_SAMPLE_INTERVAL
+// (changes happening between two polls are counted once).
+fn step_with_link_faults(
+    sys: &mut System<JsonMessage>,
+    faults: &LinkFaults,
+    group: &[String],
+    duration: f64,
+    passive: bool,
+) -> Result<HashMap<String, usize>, String> {
+    let mut timeline = MembersTimeline::new();
+    if !passive {
+        sample_members(sys, group)?;
+    }
+    timeline.observe(sys);
+    let start_time = sys.time();
+    let end_time = sys.time() + duration;
+    let mut links_up = true;
+    let mut next_toggle = sys.time() + faults.period;
+    let mut next_sample = sys.time() + FLAP_SAMPLE_INTERVAL;
+    apply_link_faults(sys, faults, links_up);
+    while sys.time() < end_time {
+        let next_time = next_toggle.min(next_sample);
+        if next_time > sys.time() {
+            sys.step_for_duration(next_time - sys.time());
+        }
+        if sys.time() >= next_toggle {
+            links_up = !links_up;
+            apply_link_faults(sys, faults, links_up);
+            next_toggle = sys.time() + faults.period;
+        }
+        if sys.time() >= next_sample {
+            if !passive {
+                sample_members(sys, group)?;
+            }
+            timeline.observe(sys);
+            next_sample = sys.time() + FLAP_SAMPLE_INTERVAL;
+        }
+    }
+    Ok(group
+        .iter()
+        .map(|node| (node.clone(), timeline.view_changes(node, start_time)))
+        .collect())
+}
+
+fn check_view_changes(view_changes: &HashMap<String, usize>, max_changes: usize) -> TestResult {
+    let mut nodes = view_changes.keys().collect::<Vec<_>>();
+    nodes.sort();
+    println!("Members list changes per node:");
+    for node in nodes {
+        println!("- [{}] {}", node, view_changes.get(node).unwrap());
+    }
+    let observed = *view_changes.values().max().unwrap();
+    assume!(
+        observed <= max_changes,
+        format!(
+            "Members lists are oscillating ({} changes on a node, max allowed {})",
+            observed, max_changes
+        )
+    )
+}
+
+// Drifting local clocks for failure detection tests: clock skew of each node changes
+// with a constant rate (e.g. rate 0.01 means that local clock runs 1% faster),
+// so local timeouts last longer or shorter than expected, and occasionally jumps forward or backward,
+// which may fire or postpone many timeouts at once.
+// Skews are updated while stepping the system through the methods below,
+// including the time spent waiting for members lists.
+struct ClockDrift {
+    skews: HashMap<String, f64>,
+    rates: HashMap<String, f64>,
+    updated: f64,
+    rand: Pcg64,
+}
+
+const CLOCK_UPDATE_INTERVAL: f64 = 0.1;
+const CLOCK_JUMP_RATE: f64 = 0.01;
+const CLOCK_MAX_JUMP: f64 = 2.;
+
+impl ClockDrift {
+    fn new(sys: &mut System<JsonMessage>, max_rate: f64, seed: u64) -> Self {
+        let mut rand = Pcg64::seed_from_u64(seed);
+        let mut skews = HashMap::new();
+        let mut rates = HashMap::new();
+        for node in sys.get_node_ids() {
+            let skew = rand.gen_range(0.0..10.0);
+            sys.set_clock_skew(&node, skew);
+            skews.insert(node.clone(), skew);
+            rates.insert(node, rand.gen_range(-max_rate..=max_rate));
+        }
+        Self {
+            skews,
+            rates,
+            updated: sys.time(),
+            rand,
+        }
+    }
+
+    // Applies the drift accumulated since the last update
+    fn update(&mut self, sys: &mut System<JsonMessage>) {
+        let elapsed = sys.time() - self.updated;
+        self.updated = sys.time();
+        let mut nodes = self.skews.keys().cloned().collect::<Vec<_>>();
+        nodes.sort();
+        for node in nodes {
+            let mut skew = self.skews[&node] + self.rates[&node] * elapsed;
+            if self.rand.gen_range(0.0..1.0) < CLOCK_JUMP_RATE * elapsed {
+                skew += self.rand.gen_range(-CLOCK_MAX_JUMP..CLOCK_MAX_JUMP);
+            }
+            sys.set_clock_skew(&node, skew);
+            self.skews.insert(node, skew);
+        }
+    }
+
+    fn step(&mut self, sys: &mut System<JsonMessage>, duration: f64) {
+        let end_time = sys.time() + duration;
+        while sys.time() < end_time {
+            sys.step_for_duration(CLOCK_UPDATE_INTERVAL.min(end_time - sys.time()));
+            self.update(sys);
+        }
+    }
+
+    fn step_until_local_message(
+        &mut self,
+        sys: &mut System<JsonMessage>,
+        node: &str,
+        timeout: f64,
+    ) -> Option<Vec<JsonMessage>> {
+        let end_time = sys.time() + timeout;
+        while sys.time() < end_time {
+            let res = sys
+                .step_until_local_message_with_timeout(node, CLOCK_UPDATE_INTERVAL)
+                .ok();
+            self.update(sys);
+            if res.is_some() {
+                return res;
+            }
+        }
+        None
+    }
+}
+
+fn step_until_stabilized_with_drift(
+    sys: &mut System<JsonMessage>,
+    clocks: &mut ClockDrift,
+    group: &[String],
+) -> TestResult {
+    let max_time = sys.time() + 300.; // timeout is 5 minutes
+    let expected: HashSet<String> = group.iter().cloned().collect();
+    let mut stabilized = false;
+    let mut views = HashMap::new();
+
+    while !stabilized && sys.time() < max_time {
+        clocks.step(sys, 5.);
+        views = sample_members_with_drift(sys, Some(&mut *clocks), group)?;
+        stabilized = views.values().all(|view| *view == expected);
+    }
+
+    if !stabilized && group.len() <= 10 {
+        println!("Members lists:");
+        for node in group.iter() {
+            let mut members = views.get(node).unwrap().iter().cloned().collect::<Vec<_>>();
+            members.sort();
+            println!("- [{}] {}", node, members.join(", "));
+        }
+    }
+    let mut unstable = group
+        .iter()
+        .filter(|node| views.get(*node) != Some(&expected))
+        .collect::<Vec<_>>();
+    unstable.sort();
+    assume!(
+        stabilized,
+        format!("Group members lists are not stabilized on {:?}", unstable)
+    )?;
+    Ok(true)
+}
+
+// Counts members list changes on each node using members timeline as in step_with_link_faults()
+fn step_with_clock_drift(
+    sys: &mut System<JsonMessage>,
+    clocks: &mut ClockDrift,
+    group: &[String],
+    duration: f64,
+    passive: bool,
+) -> Result<HashMap<String, usize>, String> {
+    let mut timeline = MembersTimeline::new();
+    if !passive {
+        sample_members_with_drift(sys, Some(&mut *clocks), group)?;
+    }
+    timeline.observe(sys);
+    let start_time = sys.time();
+    let end_time = sys.time() + duration;
+    while sys.time() < end_time {
+        clocks.step(sys, FLAP_SAMPLE_INTERVAL);
+        if !passive {
+            sample_members_with_drift(sys, Some(&mut *clocks), group)?;
+        }
+        timeline.observe(sys);
+    }
+    Ok(group
+        .iter()
+        .map(|node| (node.clone(), timeline.view_changes(node, start_time)))
+        .collect())
+}
+
+fn make_partitions(sys: &mut System<JsonMessage>, parts: &[Vec<String>]) {
+    for (i, part1) in parts.iter().enumerate() {
+        for (j, part2) in parts.iter().enumerate() {
+            if i != j {
+                for node1 in part1.iter() {
+                    for node2 in part2.iter() {
+                        sys.disable_link(node1, node2);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Member lists observed on each node over time without sending GET_MEMBERS.
+// Relies on nodes emitting MEMBERS local message each time their member list changes,
+// reading these messages from local events does not step the system.
+// Responses to GET_MEMBERS are recorded as well, so the timeline can also be built by polling.
+struct MembersTimeline {
+    views: HashMap<String, Vec<(f64, HashSet<String>)>>,
+    seen_events: HashMap<String, usize>,
+}
+
+impl MembersTimeline {
+    fn new() -> Self {
+        Self {
+            views: HashMap::new(),
+            seen_events: HashMap::new(),
+        }
+    }
+
+    fn observe(&mut self, sys: &System<JsonMessage>) {
+        for node in sys.get_node_ids() {
+            let events = sys.get_local_events(&node);
+            let seen = self.seen_events.entry(node.clone()).or_insert(0);
+            // local events are reset when a node is recovered
+            if events.len() < *seen {
+                *seen = 0;
+            }
+            let timeline = self.views.entry(node).or_default();
+            for e in events.iter().skip(*seen) {
+                if let LocalEventType::LocalMessageSend = e.tip {
+                    let msg = e.msg.as_ref().unwrap();
+                    if msg.tip == "MEMBERS" {
+                        let data: MembersMessage = serde_json::from_str(&msg.data).unwrap();
+                        let view = data.members.into_iter().collect::<HashSet<_>>();
+                        if timeline.last().map(|(_, last)| last) != Some(&view) {
+                            timeline.push((e.time, view));
+                        }
+                    }
+                }
+            }
+            *seen = events.len();
+        }
+    }
+
+    fn view(&self, node: &str) -> HashSet<String> {
+        self.views
+            .get(node)
+            .and_then(|timeline| timeline.last())
+            .map(|(_, view)| view.clone())
+            .unwrap_or_default()
+    }
+
+    fn view_changes(&self, node: &str, since: f64) -> usize {
+        self.views.get(node).map_or(0, |timeline| {
+            timeline.iter().filter(|(time, _)| *time > since).count()
+        })
+    }
+
+    // time since which the node continuously observes the given group
+    fn stable_since(&self, node: &str, group: &HashSet<String>) -> Option<f64> {
+        match self.views.get(node).and_then(|timeline| timeline.last()) {
+            Some((time, view)) if view == group => Some(*time),
+            _ => None,
+        }
+    }
+
+    fn print(&self, group: &HashSet<String>) {
+        let mut nodes = group.iter().collect::<Vec<_>>();
+        nodes.sort();
+        println!("Members lists timeline:");
+        for node in nodes {
+            println!("- [{}]", node);
+            for (time, view) in self.views.get(node).unwrap_or(&Vec::new()) {
+                let mut members = view.iter().cloned().collect::<Vec<_>>();
+                members.sort();
+                println!("  {:>9.3} {}", time, members.join(", "));
+            }
+        }
+    }
+}
+
+const OBSERVE_INTERVAL: f64 = 0.1;
+const STABLE_PERIOD: f64 = 10.;
+
+fn observe_for_duration(
+    sys: &mut System<JsonMessage>,
+    timeline: &mut MembersTimeline,
+    duration: f64,
+) {
+    let end_time = sys.time() + duration;
+    while sys.time() < end_time {
+        sys.step_for_duration(OBSERVE_INTERVAL);
+        timeline.observe(sys);
+    }
+}
+
+fn observe_until_stabilized(
+    sys: &mut System<JsonMessage>,
+    timeline: &mut MembersTimeline,
+    group: HashSet<String>,
+) -> TestResult {
+    let max_time = sys.time() + 300.; // timeout is 5 minutes
+    timeline.observe(sys);
+    let mut unstable = group.iter().cloned().collect::<Vec<_>>();
+    while !unstable.is_empty() && sys.time() < max_time {
+        observe_for_duration(sys, timeline, 1.);
+        let time = sys.time();
+        unstable = group
+            .iter()
+            .filter(|node| match timeline.stable_since(node, &group) {
+                Some(since) => time - since < STABLE_PERIOD,
+                None => true,
+            })
+            .cloned()
+            .collect();
+    }
+    let stabilized = unstable.is_empty();
+
+    if !stabilized && group.len() <= 10 {
+        timeline.print(&group);
+        let mut expected = group.clone().into_iter().collect::<Vec<_>>();
+        expected.sort();
+        println!("Expected group: {}", expected.join(", "));
+    }
+    assume!(
+        stabilized,
+        format!("Group members lists are not stabilized on {:?}", unstable)
+    )?;
+    Ok(true)
+}
+
+fn check_no_flapping(
+    sys: &mut System<JsonMessage>,
+    timeline: &mut MembersTimeline,
+    group: HashSet<String>,
+    duration: f64,
+) -> TestResult {
+    let since = sys.time();
+    observe_for_duration(sys, timeline, duration);
+    let flapping = group
+        .iter()
+        .filter(|node| timeline.view_changes(node, since) > 0 || timeline.view(node) != group)
+        .cloned()
+        .collect::<Vec<_>>();
+    if !flapping.is_empty() && group.len() <= 10 {
+        timeline.print(&group);
+    }
+    assume!(
+        flapping.is_empty(),
+        format!(
+            "Members lists changed after stabilization on {:?}",
+            flapping
+        )
+    )
+}
+
+// Network and node load counters collected over the measured part of a scenario
+struct LoadStats {
+    duration: f64,
+    traffic: u64,
+    message_count: u64,
+    node_loads: Vec<u64>,
+}
+
+struct LoadMeasurement {
+    time: f64,
+    traffic: u64,
+    message_count: u64,
+    node_loads: HashMap<String, u64>,
+}
+
+impl LoadMeasurement {
+    fn start(sys: &System<JsonMessage>, nodes: &[String]) -> Self {
+        let mut node_loads = HashMap::new();
+        for node in nodes.iter() {
+            node_loads.insert(
+                node.clone(),
+                sys.get_sent_message_count(node) + sys.get_received_message_count(node),
+            );
+        }
+        Self {
+            time: sys.time(),
+            traffic: sys.get_network_traffic(),
+            message_count: sys.get_network_message_count(),
+            node_loads,
+        }
+    }
+
+    fn finish(&self, sys: &System<JsonMessage>, nodes: &[String]) -> LoadStats {
+        let mut node_loads = Vec::new();
+        for node in nodes.iter() {
+            let load = sys.get_sent_message_count(node) + sys.get_received_message_count(node);
+            node_loads.push(load - self.node_loads.get(node).unwrap());
+        }
+        LoadStats {
+            duration: sys.time() - self.time,
+            traffic: sys.get_network_traffic() - self.traffic,
+            message_count: sys.get_network_message_count() - self.message_count,
+            node_loads,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Growth {
+    Log,
+    NLogN,
+}
+
+impl Growth {
+    fn eval(&self, n: f64) -> f64 {
+        match self {
+            Growth::Log => n.ln(),
+            Growth::NLogN => n * n.ln(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Growth::Log => "O(log N)",
+            Growth::NLogN => "O(N log N)",
+        }
+    }
+}
+
+struct Metric {
+    name: &'static str,
+    extract: fn(&LoadStats) -> f64,
+    // metric should grow at most 2X times when the number of nodes grows X times
+    check_scaling: bool,
+    // asymptotic bound on metric growth with the number of nodes
+    max_growth: Option<Growth>,
+    // bound on metric value for each number of nodes
+    max_value: Option<f64>,
+}
+
+// allowed excess of the fitted growth exponent over the exponent of the bound
+const GROWTH_TOLERANCE: f64 = 0.2;
+
+// Fits exponent b in y = a * x^b using least squares on log-log scale
+fn fit_growth_exponent(xs: &[f64], ys: &[f64]) -> f64 {
+    let log_xs: Vec<f64> = xs.iter().map(|x| x.ln()).collect();
+    let log_ys: Vec<f64> = ys.iter().map(|y| y.max(1e-9).ln()).collect();
+    let mean_x = log_xs.iter().sum::<f64>() / log_xs.len() as f64;
+    let mean_y = log_ys.iter().sum::<f64>() / log_ys.len() as f64;
+    let mut cov = 0.;
+    let mut var = 0.;
+    for (x, y) in log_xs.iter().zip(log_ys.iter()) {
+        cov += (x - mean_x) * (y - mean_y);
+        var += (x - mean_x) * (x - mean_x);
+    }
+    cov / var
+}
+
+fn write_csv(
+    dir: &str,
+    name: &str,
+    sizes: &[u32],
+    metrics: &[Metric],
+    values: &[Vec<f64>],
+) -> Result<(), String> {
+    let path = format!("{}/{}.csv", dir, name);
+    let write = || -> std::io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let mut file = File::create(&path)?;
+        let header: Vec<&str> = metrics.iter().map(|m| m.name).collect();
+        writeln!(file, "N,{}", header.join(","))?;
+        for (i, size) in sizes.iter().enumerate() {
+            let row: Vec<String> = values.iter().map(|v| format!("{}", v[i])).collect();
+            writeln!(file, "{},{}", size, row.join(","))?;
+        }
+        Ok(())
+    };
+    write().map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+// Runs scenario for each number of nodes and returns values[metric][size]
+fn measure_metrics<F>(
+    config: &TestConfig,
+    scenario: &F,
+    sizes: &[u32],
+    metrics: &[Metric],
+) -> Result<Vec<Vec<f64>>, String>
+where
+    F: Fn(&TestConfig) -> Result<LoadStats, String>,
+{
+    let mut values = vec![Vec::new(); metrics.len()];
+    for node_count in sizes.iter() {
+        let mut run_config = config.clone();
+        run_config.node_count = *node_count;
+        let stats = scenario(&run_config)?;
+        let row: Vec<String> = metrics
+            .iter()
+            .enumerate()
+            .map(|(i, metric)| {
+                let value = (metric.extract)(&stats);
+                values[i].push(value);
+                format!("{} - {:.2}", metric.name, value)
+            })
+            .collect();
+        println!("- N = {}: {}", node_count, row.join(", "));
+    }
+    Ok(values)
+}
+
+// Runs scenario for each number of nodes and checks how the measured metrics grow
+fn run_scalability<F>(
+    name: &str,
+    config: &TestConfig,
+    scenario: F,
+    sizes: &[u32],
+    metrics: &[Metric],
+) -> TestResult
+where
+    F: Fn(&TestConfig) -> Result<LoadStats, String>,
+{
+    let values = measure_metrics(config, &scenario, sizes, metrics)?;
+    // compare with the reference implementation if requested and it is not the one being measured
+    let mut baseline_values = None;
+    if config.compare_reference && config.reference_ratio < 1. {
+        println!("Baseline (reference implementation):");
+        let mut baseline_config = config.clone();
+        baseline_config.reference_ratio = 1.;
+        baseline_values = Some(measure_metrics(
+            &baseline_config,
+            &scenario,
+            sizes,
+            metrics,
+        )?);
+    }
+    if let Some(csv_dir) = &config.csv_dir {
+        write_csv(csv_dir, name, sizes, metrics, &values)?;
+        if let Some(baseline_values) = &baseline_values {
+            write_csv(
+                csv_dir,
+                &format!("{}_baseline", name),
+                sizes,
+                metrics,
+                baseline_values,
+            )?;
+        }
+    }
+
+    let xs: Vec<f64> = sizes.iter().map(|x| *x as f64).collect();
+    let mut scaling_ok = true;
+    let mut values_ok = true;
+    println!("Growth exponents:");
+    for (i, (metric, ys)) in metrics.iter().zip(values.iter()).enumerate() {
+        let exponent = fit_growth_exponent(&xs, ys);
+        if let Some(baseline_values) = &baseline_values {
+            let baseline_exponent = fit_growth_exponent(&xs, &baseline_values[i]);
+            println!("  - {} (baseline): {:.2}", metric.name, baseline_exponent);
+        }
+        match metric.max_growth {
+            Some(growth) => {
+                let bound_ys: Vec<f64> = xs.iter().map(|x| growth.eval(*x)).collect();
+                let max_exponent = fit_growth_exponent(&xs, &bound_ys);
+                println!(
+                    "  - {}: {:.2} (max {:.2} for {})",
+                    metric.name,
+                    exponent,
+                    max_exponent,
+                    growth.name()
+                );
+                if exponent > max_exponent + GROWTH_TOLERANCE {
+                    scaling_ok = false;
+                }
+            }
+            None => println!("  - {}: {:.2}", metric.name, exponent),
+        }
+        if metric.check_scaling {
+            for j in 1..xs.len() {
+                let size_ratio = xs[j] / xs[j - 1];
+                if ys[j] / ys[j - 1] > 2.0 * size_ratio {
+                    scaling_ok = false;
+                }
+            }
+        }
+        if let Some(max_value) = metric.max_value {
+            if ys.iter().any(|y| *y > max_value) {
+                values_ok = false;
+            }
+        }
+    }
+    assume!(scaling_ok, "Bad network load scaling")?;
+    assume!(values_ok, "Bad max/min node load")?;
+    Ok(true)
+}
+
 // TESTS -------------------------------------------------------------------------------------------
 
 fn test_simple(config: &TestConfig) -> TestResult {
@@ -246,6 +980,117 @@ fn test_node_crash_recover(config: &TestConfig) -> TestResult {
     step_until_stabilized(&mut sys, group.into_iter().collect())
 }
 
+fn test_node_crash_restart(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().cloned().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // node crashes and quickly restarts with the same id
+    // (before other nodes could detect the failure)
+    let crashed_node = group.get(rand.gen_range(1..group.len())).unwrap().clone();
+    sys.crash_node(&crashed_node);
+    sys.step_for_duration(0.5);
+    recover_node(&crashed_node, &mut sys, config);
+    sys.send_local(
+        JsonMessage::from("JOIN", &JoinMessage { seed }),
+        &crashed_node,
+    );
+    step_until_stabilized_without_duplicates(&mut sys, group.into_iter().collect())
+}
+
+fn test_node_crash_restart_repeated(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().cloned().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // node crashes and restarts several times in a row
+    // (some restarts happen before, some after the failure is detected)
+    let crashed_node = group.get(rand.gen_range(1..group.len())).unwrap().clone();
+    for _ in 0..5 {
+        sys.crash_node(&crashed_node);
+        let downtime = rand.gen_range(0.1..10.0);
+        sys.step_for_duration(downtime);
+        recover_node(&crashed_node, &mut sys, config);
+        sys.send_local(
+            JsonMessage::from("JOIN", &JoinMessage { seed }),
+            &crashed_node,
+        );
+        let uptime = rand.gen_range(0.1..5.0);
+        sys.step_for_duration(uptime);
+    }
+    step_until_stabilized_without_duplicates(&mut sys, group.into_iter().collect())
+}
+
+fn test_seed_node_crash_restart(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().cloned().unwrap();
+    let new_seed = &group.get(1).cloned().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // seed node crashes and quickly restarts joining via another node
+    sys.crash_node(seed);
+    sys.step_for_duration(0.5);
+    recover_node(seed, &mut sys, config);
+    sys.send_local(
+        JsonMessage::from("JOIN", &JoinMessage { seed: new_seed }),
+        seed,
+    );
+    step_until_stabilized_without_duplicates(&mut sys, group.into_iter().collect())
+}
+
+fn test_node_false_suspicion(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // alive node is briefly unreachable from half of the group
+    // (it can be suspected by some nodes, but should not be excluded forever)
+    let suspected_node = group.get(rand.gen_range(0..group.len())).unwrap();
+    for node in group.iter().filter(|node| *node != suspected_node) {
+        if rand.gen_range(0.0..1.0) < 0.5 {
+            sys.disable_link(node, suspected_node);
+            sys.disable_link(suspected_node, node);
+        }
+    }
+    sys.step_for_duration(5.);
+    sys.reset_network();
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // alive node is briefly disconnected from all nodes
+    sys.disconnect_node(suspected_node);
+    sys.step_for_duration(5.);
+    sys.connect_node(suspected_node);
+    step_until_stabilized(&mut sys, group.into_iter().collect())
+}
+
 fn test_node_offline(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
@@ -274,144 +1119,414 @@ fn test_seed_node_offline(config: &TestConfig) -> TestResult {
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
-    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
-
-    // seed node goes offline
-    group.remove(0);
-    sys.disconnect_node(&seed);
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // seed node goes offline
+    group.remove(0);
+    sys.disconnect_node(&seed);
+    step_until_stabilized(&mut sys, group.into_iter().collect())
+}
+
+fn test_node_offline_recover(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.get(0).unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // node goes offline
+    let offline_node = group.remove(rand.gen_range(0..group.len()));
+    sys.disconnect_node(&offline_node);
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // node goes back online
+    sys.connect_node(&offline_node);
+    group.push(offline_node);
+    step_until_stabilized(&mut sys, group.into_iter().collect())
+}
+
+fn test_network_partition(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.get(0).unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // network is partitioned
+    let (group1, group2): (Vec<_>, Vec<_>) = group
+        .iter()
+        .map(|s| &**s)
+        .partition(|_| rand.gen_range(0.0..1.0) > 0.6);
+    sys.make_partition(&group1, &group2);
+    step_until_stabilized(&mut sys, group1.into_iter().map(String::from).collect())?;
+    step_until_stabilized(&mut sys, group2.into_iter().map(String::from).collect())
+}
+
+fn test_network_partition_recover(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.get(0).unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // network is partitioned
+    let (group1, group2): (Vec<_>, Vec<_>) = group
+        .iter()
+        .map(|s| &**s)
+        .partition(|_| rand.gen_range(0.0..1.0) > 0.6);
+    sys.make_partition(&group1, &group2);
+    step_until_stabilized(&mut sys, group1.into_iter().map(String::from).collect())?;
+    step_until_stabilized(&mut sys, group2.into_iter().map(String::from).collect())?;
+
+    // network is recovered
+    sys.reset_network();
+    step_until_stabilized(&mut sys, group.into_iter().map(String::from).collect())
+}
+
+const SPLIT_BRAIN_SIZE_FACTOR: u32 = 3;
+const SPLIT_BRAIN_PARTS: usize = 3;
+const SPLIT_BRAIN_DURATION: f64 = 600.;
+
+fn test_split_brain(config: &TestConfig, join_during_partition: bool) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut run_config = config.clone();
+    run_config.node_count = config.node_count * SPLIT_BRAIN_SIZE_FACTOR;
+    let mut sys = build_system(&run_config);
+    let mut nodes = sys.get_node_ids();
+    nodes.shuffle(&mut rand);
+
+    // some nodes are kept aside to join the group later (one per partition)
+    let mut new_nodes = Vec::new();
+    if join_during_partition {
+        for _ in 0..SPLIT_BRAIN_PARTS {
+            new_nodes.push(nodes.pop().unwrap());
+        }
+    }
+    let group = nodes;
+    let seed = &group.first().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // network is split into several partitions
+    let mut parts = vec![Vec::new(); SPLIT_BRAIN_PARTS];
+    for (i, node) in group.iter().enumerate() {
+        parts[i % SPLIT_BRAIN_PARTS].push(node.clone());
+    }
+    for (i, node) in new_nodes.iter().enumerate() {
+        parts[i].push(node.clone());
+    }
+    make_partitions(&mut sys, &parts);
+    for part in parts.iter() {
+        let part_group: HashSet<String> = part
+            .iter()
+            .filter(|node| !new_nodes.contains(node))
+            .cloned()
+            .collect();
+        step_until_stabilized(&mut sys, part_group)?;
+    }
+
+    // new nodes join each partition
+    if join_during_partition {
+        for (i, node) in new_nodes.iter().enumerate() {
+            let seed = &parts[i][0];
+            sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+        }
+        for part in parts.iter() {
+            step_until_stabilized(&mut sys, part.clone().into_iter().collect())?;
+        }
+    }
+
+    // partitions run independently for a long time
+    sys.step_for_duration(SPLIT_BRAIN_DURATION);
+    for part in parts.iter() {
+        step_until_stabilized(&mut sys, part.clone().into_iter().collect())?;
+    }
+
+    // network is recovered, partitions should merge into a single group
+    sys.reset_network();
+    let merge_start = sys.time();
+    let merged_group = parts.into_iter().flatten().collect();
+    step_until_stabilized(&mut sys, merged_group)?;
+    if config.debug {
+        println!("Merge time: {:.2}", sys.time() - merge_start);
+    }
+    Ok(true)
+}
+
+fn test_split_brain_merge(config: &TestConfig) -> TestResult {
+    test_split_brain(config, false)
+}
+
+fn test_split_brain_merge_with_joins(config: &TestConfig) -> TestResult {
+    test_split_brain(config, true)
+}
+
+fn test_node_cannot_receive(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.get(0).unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // node goes partially offline (cannot receive incoming messages)
+    let blocked_node = group.remove(rand.gen_range(0..group.len()));
+    sys.drop_incoming(&blocked_node);
+    step_until_stabilized(&mut sys, group.into_iter().collect())
+}
+
+fn test_node_cannot_send(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.get(0).unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // node goes partially offline (cannot send outgoing messages)
+    let blocked_node = group.remove(rand.gen_range(0..group.len()));
+    sys.drop_outgoing(&blocked_node);
+    step_until_stabilized(&mut sys, group.into_iter().collect())
+}
+
+fn test_two_nodes_cannot_communicate(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.get(0).unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+
+    // two nodes cannot communicate with each other
+    let node1 = *seed;
+    let mut node2 = group.get(rand.gen_range(0..group.len())).unwrap();
+    while node1 == node2 {
+        node2 = group.get(rand.gen_range(0..group.len())).unwrap();
+    }
+    sys.disable_link(&node1, &node2);
+    sys.disable_link(&node2, &node1);
+    // run for a while
+    sys.steps(1000);
     step_until_stabilized(&mut sys, group.into_iter().collect())
 }
 
-fn test_node_offline_recover(config: &TestConfig) -> TestResult {
+fn test_flapping_link(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
     let mut group = sys.get_node_ids();
     group.shuffle(&mut rand);
-    let seed = &group.get(0).unwrap();
+    let seed = &group.first().unwrap();
 
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
     step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
 
-    // node goes offline
-    let offline_node = group.remove(rand.gen_range(0..group.len()));
-    sys.disconnect_node(&offline_node);
-    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+    // link between two nodes periodically goes down and up
+    let node1 = group.first().unwrap().clone();
+    let node2 = group.get(rand.gen_range(1..group.len())).unwrap().clone();
+    let faults = LinkFaults {
+        flapping: vec![(node1.clone(), node2.clone()), (node2, node1)],
+        broken: Vec::new(),
+        period: config.flap_period,
+    };
+    let view_changes =
+        step_with_link_faults(&mut sys, &faults, &group, FLAP_DURATION, config.passive)?;
+    check_view_changes(&view_changes, faults.max_view_changes(FLAP_DURATION))?;
 
-    // node goes back online
-    sys.connect_node(&offline_node);
-    group.push(offline_node);
+    // network is recovered
+    sys.reset_network();
     step_until_stabilized(&mut sys, group.into_iter().collect())
 }
 
-fn test_network_partition(config: &TestConfig) -> TestResult {
+fn test_flapping_node(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
     let mut group = sys.get_node_ids();
     group.shuffle(&mut rand);
-    let seed = &group.get(0).unwrap();
+    let seed = &group.first().unwrap();
 
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
     step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
 
-    // network is partitioned
-    let (group1, group2): (Vec<_>, Vec<_>) = group
-        .iter()
-        .map(|s| &**s)
-        .partition(|_| rand.gen_range(0.0..1.0) > 0.6);
-    sys.make_partition(&group1, &group2);
-    step_until_stabilized(&mut sys, group1.into_iter().map(String::from).collect())?;
-    step_until_stabilized(&mut sys, group2.into_iter().map(String::from).collect())
+    // all links of a node periodically go down and up
+    let flapping_node = group.get(rand.gen_range(0..group.len())).unwrap().clone();
+    let mut flapping = Vec::new();
+    for node in group.iter().filter(|node| **node != flapping_node) {
+        flapping.push((node.clone(), flapping_node.clone()));
+        flapping.push((flapping_node.clone(), node.clone()));
+    }
+    let faults = LinkFaults {
+        flapping,
+        broken: Vec::new(),
+        period: config.flap_period,
+    };
+    let view_changes =
+        step_with_link_faults(&mut sys, &faults, &group, FLAP_DURATION, config.passive)?;
+    check_view_changes(&view_changes, faults.max_view_changes(FLAP_DURATION))?;
+
+    // network is recovered
+    sys.reset_network();
+    step_until_stabilized(&mut sys, group.into_iter().collect())
 }
 
-fn test_network_partition_recover(config: &TestConfig) -> TestResult {
+fn test_asymmetric_links(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
     let mut group = sys.get_node_ids();
     group.shuffle(&mut rand);
-    let seed = &group.get(0).unwrap();
+    let seed = &group.first().unwrap();
 
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
     step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
 
-    // network is partitioned
-    let (group1, group2): (Vec<_>, Vec<_>) = group
-        .iter()
-        .map(|s| &**s)
-        .partition(|_| rand.gen_range(0.0..1.0) > 0.6);
-    sys.make_partition(&group1, &group2);
-    step_until_stabilized(&mut sys, group1.into_iter().map(String::from).collect())?;
-    step_until_stabilized(&mut sys, group2.into_iter().map(String::from).collect())?;
+    // some links are broken in one direction for a long time
+    // (all nodes can still communicate with others, so the group should not change)
+    let mut broken = Vec::new();
+    for _ in 0..group.len() / 2 {
+        let from = group.choose(&mut rand).unwrap().clone();
+        let mut to = group.choose(&mut rand).unwrap().clone();
+        while from == to {
+            to = group.choose(&mut rand).unwrap().clone();
+        }
+        broken.push((from, to));
+    }
+    let faults = LinkFaults {
+        flapping: Vec::new(),
+        broken,
+        period: FLAP_DURATION,
+    };
+    let view_changes =
+        step_with_link_faults(&mut sys, &faults, &group, FLAP_DURATION, config.passive)?;
+    check_view_changes(&view_changes, faults.max_view_changes(FLAP_DURATION))?;
 
     // network is recovered
     sys.reset_network();
-    step_until_stabilized(&mut sys, group.into_iter().map(String::from).collect())
+    step_until_stabilized(&mut sys, group.into_iter().collect())
 }
 
-fn test_node_cannot_receive(config: &TestConfig) -> TestResult {
+fn test_flapping_and_asymmetric_links(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
     let mut group = sys.get_node_ids();
     group.shuffle(&mut rand);
-    let seed = &group.get(0).unwrap();
+    let seed = &group.first().unwrap();
 
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
     step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
 
-    // node goes partially offline (cannot receive incoming messages)
-    let blocked_node = group.remove(rand.gen_range(0..group.len()));
-    sys.drop_incoming(&blocked_node);
+    // some links are flapping, some are broken in one direction
+    let mut flapping = Vec::new();
+    let mut broken = Vec::new();
+    for _ in 0..group.len() / 2 {
+        let from = group.choose(&mut rand).unwrap().clone();
+        let mut to = group.choose(&mut rand).unwrap().clone();
+        while from == to {
+            to = group.choose(&mut rand).unwrap().clone();
+        }
+        if rand.gen_range(0.0..1.0) < 0.5 {
+            flapping.push((to.clone(), from.clone()));
+            flapping.push((from, to));
+        } else {
+            broken.push((from, to));
+        }
+    }
+    let faults = LinkFaults {
+        flapping,
+        broken,
+        period: config.flap_period,
+    };
+    let view_changes =
+        step_with_link_faults(&mut sys, &faults, &group, FLAP_DURATION, config.passive)?;
+    check_view_changes(&view_changes, faults.max_view_changes(FLAP_DURATION))?;
+
+    // network is recovered
+    sys.reset_network();
     step_until_stabilized(&mut sys, group.into_iter().collect())
 }
 
-fn test_node_cannot_send(config: &TestConfig) -> TestResult {
+fn test_clock_drift(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
+    let mut clocks = ClockDrift::new(&mut sys, config.clock_drift, config.seed);
     let mut group = sys.get_node_ids();
     group.shuffle(&mut rand);
-    let seed = &group.get(0).unwrap();
+    let seed = &group.first().unwrap();
 
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
-    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+    step_until_stabilized_with_drift(&mut sys, &mut clocks, &group)?;
 
-    // node goes partially offline (cannot send outgoing messages)
-    let blocked_node = group.remove(rand.gen_range(0..group.len()));
-    sys.drop_outgoing(&blocked_node);
-    step_until_stabilized(&mut sys, group.into_iter().collect())
+    // clocks drift for a long time (no node should be falsely excluded)
+    let view_changes = step_with_clock_drift(&mut sys, &mut clocks, &group, 300., config.passive)?;
+    check_view_changes(&view_changes, 0)
 }
 
-fn test_two_nodes_cannot_communicate(config: &TestConfig) -> TestResult {
+fn test_clock_drift_node_crash(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut sys = build_system(config);
+    let mut clocks = ClockDrift::new(&mut sys, config.clock_drift, config.seed);
     let mut group = sys.get_node_ids();
     group.shuffle(&mut rand);
-    let seed = &group.get(0).unwrap();
+    let seed = &group.first().cloned().unwrap();
 
     for node in &group {
         sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
-    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+    step_until_stabilized_with_drift(&mut sys, &mut clocks, &group)?;
+    clocks.step(&mut sys, 100.);
 
-    // two nodes cannot communicate with each other
-    let node1 = *seed;
-    let mut node2 = group.get(rand.gen_range(0..group.len())).unwrap();
-    while node1 == node2 {
-        node2 = group.get(rand.gen_range(0..group.len())).unwrap();
-    }
-    sys.disable_link(&node1, &node2);
-    sys.disable_link(&node2, &node1);
-    // run for a while
-    sys.steps(1000);
-    step_until_stabilized(&mut sys, group.into_iter().collect())
+    // node crashes (should be detected by nodes with both slow and fast clocks)
+    let crashed_node = group.remove(rand.gen_range(1..group.len()));
+    sys.crash_node(&crashed_node);
+    step_until_stabilized_with_drift(&mut sys, &mut clocks, &group)?;
+    clocks.step(&mut sys, 100.);
+
+    // node recovers
+    recover_node(&crashed_node, &mut sys, config);
+    sys.send_local(
+        JsonMessage::from("JOIN", &JoinMessage { seed }),
+        &crashed_node,
+    );
+    group.push(crashed_node);
+    step_until_stabilized_with_drift(&mut sys, &mut clocks, &group)
 }
 
 fn test_slow_network(config: &TestConfig) -> TestResult {
@@ -537,155 +1652,299 @@ fn test_chaos_monkey(config: &TestConfig) -> TestResult {
     Ok(true)
 }
 
-fn test_scalability_normal(config: &TestConfig) -> TestResult {
-    let sys_sizes = [
-        config.node_count,
-        config.node_count * 2,
-        config.node_count * 5,
-        config.node_count * 10,
-    ];
-    let mut measurements = Vec::new();
-    for node_count in sys_sizes {
-        let mut run_config = config.clone();
-        run_config.node_count = node_count;
-        let mut rand = Pcg64::seed_from_u64(config.seed);
-        let mut sys = build_system(&run_config);
-        let mut group = sys.get_node_ids();
-        group.shuffle(&mut rand);
-        let seed = &group.get(0).unwrap();
-        for node in &group {
-            sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
-        }
+const CHURN_DURATION: f64 = 300.;
+const CHURN_SAMPLE_INTERVAL: f64 = 5.;
+const CHURN_MIN_COMPLETENESS: f64 = 0.9;
+const CHURN_MAX_STALENESS: f64 = 0.1;
 
-        step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
-        let init_time = sys.time();
-        let init_net_traffic = sys.get_network_traffic();
-        let init_msg_count = sys.get_network_message_count();
-        let mut init_loads = HashMap::new();
-        for node in sys.get_node_ids() {
-            init_loads.insert(
-                node.clone(),
-                sys.get_sent_message_count(&node) + sys.get_received_message_count(&node),
-            );
+fn test_churn(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut alive = sys.get_node_ids();
+    alive.shuffle(&mut rand);
+    let seed = &alive.first().cloned().unwrap();
+
+    for node in &alive {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, alive.clone().into_iter().collect())?;
+
+    // nodes join, leave, crash and restart continuously without waiting for stabilization
+    let min_alive = alive.len() - alive.len() / 2;
+    let mut left = Vec::new();
+    let mut crashed = Vec::new();
+    let mut completeness = Vec::new();
+    let mut staleness = Vec::new();
+    let end_time = sys.time() + CHURN_DURATION;
+    let mut next_event = sys.time() - (1. - rand.gen_range(0.0..1.0f64)).ln() / config.churn_rate;
+    let mut next_sample = sys.time() + CHURN_SAMPLE_INTERVAL;
+    while sys.time() < end_time {
+        let next_time = next_event.min(next_sample);
+        if next_time > sys.time() {
+            sys.step_for_duration(next_time - sys.time());
         }
 
-        sys.step_for_duration(10.0);
+        if sys.time() >= next_event {
+            let p = rand.gen_range(0.0..1.0);
+            match p {
+                p if p < 0.25 && alive.len() > min_alive => {
+                    // node leaves
+                    let left_node = alive.remove(rand.gen_range(0..alive.len()));
+                    sys.send_local(JsonMessage::from("LEAVE", &LeaveMessage {}), &left_node);
+                    left.push(left_node);
+                }
+                p if p < 0.5 && alive.len() > min_alive => {
+                    // node crashes
+                    let crashed_node = alive.remove(rand.gen_range(0..alive.len()));
+                    sys.crash_node(&crashed_node);
+                    crashed.push(crashed_node);
+                }
+                p if p < 0.75 && !crashed.is_empty() => {
+                    // crashed node restarts
+                    let restarted_node = crashed.remove(rand.gen_range(0..crashed.len()));
+                    let seed = alive.choose(&mut rand).unwrap();
+                    recover_node(&restarted_node, &mut sys, config);
+                    sys.send_local(
+                        JsonMessage::from("JOIN", &JoinMessage { seed }),
+                        &restarted_node,
+                    );
+                    alive.push(restarted_node);
+                }
+                _ if !left.is_empty() => {
+                    // left node joins again
+                    let joined_node = left.remove(rand.gen_range(0..left.len()));
+                    let seed = alive.choose(&mut rand).unwrap();
+                    sys.send_local(
+                        JsonMessage::from("JOIN", &JoinMessage { seed }),
+                        &joined_node,
+                    );
+                    alive.push(joined_node);
+                }
+                _ => {}
+            }
+            next_event = sys.time() - (1. - rand.gen_range(0.0..1.0f64)).ln() / config.churn_rate;
+        }
 
-        let mut loads = Vec::new();
-        for node in sys.get_node_ids() {
-            let load = sys.get_sent_message_count(&node) + sys.get_received_message_count(&node);
-            loads.push(load - init_loads.get(&node).unwrap());
-        }
-        let min_load = *loads.iter().min().unwrap();
-        let max_load = *loads.iter().max().unwrap();
-        let duration = sys.time() - init_time;
-        measurements.push((
-            duration,
-            (sys.get_network_traffic() - init_net_traffic) as f64 / duration,
-            (sys.get_network_message_count() - init_msg_count) as f64 / duration,
-            max_load as f64 / duration,
-            max_load as f64 / min_load as f64,
-        ));
-    }
-    let mut scaling_ok = true;
-    let mut load_ratio_ok = true;
-    for i in 0..sys_sizes.len() {
-        let (time, traffic, message_count, max_load, load_ratio) = measurements[i];
-        println!("- N = {}: time - {:.2}, traffic/s - {:.2}, messages/s - {:.2}, max node messages/s - {:.2}, max/min node load - {:.2}",
-                 sys_sizes[i], time, traffic, message_count, max_load, load_ratio);
-        if load_ratio > 5.0 {
-            load_ratio_ok = false;
-        }
-        if i > 0 {
-            let size_ratio = sys_sizes[i] as f64 / sys_sizes[i - 1] as f64;
-            let traffic_ratio = traffic / measurements[i - 1].1;
-            let messages_ratio = message_count / measurements[i - 1].2;
-            if traffic_ratio > 2.0 * size_ratio || messages_ratio > 2.0 * size_ratio {
-                scaling_ok = false;
+        if sys.time() >= next_sample {
+            let alive_set: HashSet<String> = alive.clone().into_iter().collect();
+            let dead_set: HashSet<String> = left.iter().chain(crashed.iter()).cloned().collect();
+            let views = sample_members(&mut sys, &alive)?;
+            for view in views.values() {
+                // fraction of alive members known to the node
+                let known_alive = view.intersection(&alive_set).count();
+                completeness.push(known_alive as f64 / alive_set.len() as f64);
+                // fraction of dead members still listed by the node
+                if !dead_set.is_empty() {
+                    let known_dead = view.intersection(&dead_set).count();
+                    staleness.push(known_dead as f64 / dead_set.len() as f64);
+                }
             }
+            next_sample = sys.time() + CHURN_SAMPLE_INTERVAL;
         }
     }
-    assume!(scaling_ok, "Bad network load scaling")?;
-    assume!(load_ratio_ok, "Bad max/min node load")?;
-    Ok(true)
+
+    let avg_completeness = completeness.iter().sum::<f64>() / completeness.len() as f64;
+    let avg_staleness = match staleness.len() {
+        0 => 0.,
+        n => staleness.iter().sum::<f64>() / n as f64,
+    };
+    println!("\nMembers lists under churn:");
+    println!("  - average completeness: {:.3}", avg_completeness);
+    println!("  - average staleness: {:.3}", avg_staleness);
+    assume!(
+        avg_completeness >= CHURN_MIN_COMPLETENESS,
+        format!(
+            "Average completeness is below {:.2}",
+            CHURN_MIN_COMPLETENESS
+        )
+    )?;
+    assume!(
+        avg_staleness <= CHURN_MAX_STALENESS,
+        format!("Average staleness is above {:.2}", CHURN_MAX_STALENESS)
+    )
 }
 
-fn test_scalability_crash(config: &TestConfig) -> TestResult {
-    let sys_sizes = [
+fn build_joined_system(
+    config: &TestConfig,
+    rand: &mut Pcg64,
+) -> Result<(System<JsonMessage>, Vec<String>), String> {
+    let mut sys = build_system(config);
+    let mut group = sys.get_node_ids();
+    group.shuffle(rand);
+    let seed = &group.first().unwrap();
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+    Ok((sys, group))
+}
+
+fn scenario_normal(config: &TestConfig) -> Result<LoadStats, String> {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let (mut sys, group) = build_joined_system(config, &mut rand)?;
+    let measurement = LoadMeasurement::start(&sys, &group);
+    sys.step_for_duration(10.0);
+    Ok(measurement.finish(&sys, &group))
+}
+
+fn scenario_crash(config: &TestConfig) -> Result<LoadStats, String> {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let (mut sys, mut group) = build_joined_system(config, &mut rand)?;
+    let measurement = LoadMeasurement::start(&sys, &group);
+    let crashed_node = group.remove(rand.gen_range(0..group.len()));
+    sys.crash_node(&crashed_node);
+    step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+    Ok(measurement.finish(&sys, &group))
+}
+
+fn membership_metrics() -> Vec<Metric> {
+    vec![
+        Metric {
+            name: "time",
+            extract: |stats| stats.duration,
+            check_scaling: false,
+            max_growth: None,
+            max_value: None,
+        },
+        Metric {
+            name: "traffic/s",
+            extract: |stats| stats.traffic as f64 / stats.duration,
+            check_scaling: true,
+            max_growth: Some(Growth::NLogN),
+            max_value: None,
+        },
+        Metric {
+            name: "messages/s",
+            extract: |stats| stats.message_count as f64 / stats.duration,
+            check_scaling: true,
+            max_growth: Some(Growth::NLogN),
+            max_value: None,
+        },
+        Metric {
+            name: "max node messages/s",
+            extract: |stats| *stats.node_loads.iter().max().unwrap() as f64 / stats.duration,
+            check_scaling: false,
+            max_growth: Some(Growth::Log),
+            max_value: None,
+        },
+        Metric {
+            name: "max/min node load",
+            extract: |stats| {
+                *stats.node_loads.iter().max().unwrap() as f64
+                    / *stats.node_loads.iter().min().unwrap() as f64
+            },
+            check_scaling: false,
+            max_growth: None,
+            max_value: Some(5.0),
+        },
+    ]
+}
+
+fn scalability_sizes(config: &TestConfig) -> Vec<u32> {
+    vec![
         config.node_count,
         config.node_count * 2,
         config.node_count * 5,
         config.node_count * 10,
-    ];
-    let mut measurements = Vec::new();
-    for node_count in sys_sizes {
-        let mut run_config = config.clone();
-        run_config.node_count = node_count;
-        let mut rand = Pcg64::seed_from_u64(config.seed);
-        let mut sys = build_system(&run_config);
-        let mut group = sys.get_node_ids();
-        group.shuffle(&mut rand);
-        let seed = &group.get(0).unwrap();
-        for node in &group {
-            sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
-        }
+    ]
+}
 
-        step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
-        let init_time = sys.time();
-        let init_net_traffic = sys.get_network_traffic();
-        let init_msg_count = sys.get_network_message_count();
-        let mut init_loads = HashMap::new();
-        for node in sys.get_node_ids() {
-            init_loads.insert(
-                node.clone(),
-                sys.get_sent_message_count(&node) + sys.get_received_message_count(&node),
-            );
-        }
+fn test_scalability_normal(config: &TestConfig) -> TestResult {
+    run_scalability(
+        "scalability_normal",
+        config,
+        scenario_normal,
+        &scalability_sizes(config),
+        &membership_metrics(),
+    )
+}
 
-        let crashed_node = group.remove(rand.gen_range(0..group.len()));
-        sys.crash_node(&crashed_node);
-        step_until_stabilized(&mut sys, group.clone().into_iter().collect())?;
+fn test_scalability_crash(config: &TestConfig) -> TestResult {
+    run_scalability(
+        "scalability_crash",
+        config,
+        scenario_crash,
+        &scalability_sizes(config),
+        &membership_metrics(),
+    )
+}
 
-        let mut loads = Vec::new();
-        for node in sys.get_node_ids() {
-            if node != crashed_node {
-                let load =
-                    sys.get_sent_message_count(&node) + sys.get_received_message_count(&node);
-                loads.push(load - init_loads.get(&node).unwrap());
-            }
-        }
-        let min_load = *loads.iter().min().unwrap();
-        let max_load = *loads.iter().max().unwrap();
-        let duration = sys.time() - init_time;
-        measurements.push((
-            duration,
-            (sys.get_network_traffic() - init_net_traffic) as f64 / duration,
-            (sys.get_network_message_count() - init_msg_count) as f64 / duration,
-            max_load as f64 / duration,
-            max_load as f64 / min_load as f64,
-        ));
+fn test_passive_simple(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config);
+    let mut timeline = MembersTimeline::new();
+    let seed = "0";
+    for node in sys.get_node_ids() {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), &node);
     }
-    let mut scaling_ok = true;
-    let mut load_ratio_ok = true;
-    for i in 0..sys_sizes.len() {
-        let (time, traffic, message_count, max_load, load_ratio) = measurements[i];
-        println!("- N = {}: time - {:.2}, traffic/s - {:.2}, messages/s - {:.2}, max node messages/s - {:.2}, max/min node load - {:.2}",
-                 sys_sizes[i], time, traffic, message_count, max_load, load_ratio);
-        if load_ratio > 5.0 {
-            load_ratio_ok = false;
-        }
-        if i > 0 {
-            let size_ratio = sys_sizes[i] as f64 / sys_sizes[i - 1] as f64;
-            let traffic_ratio = traffic / measurements[i - 1].1;
-            let messages_ratio = message_count / measurements[i - 1].2;
-            if traffic_ratio > 2.0 * size_ratio || messages_ratio > 2.0 * size_ratio {
-                scaling_ok = false;
-            }
-        }
+    let group: HashSet<String> = sys.get_node_ids().into_iter().collect();
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone())?;
+    check_no_flapping(&mut sys, &mut timeline, group, 60.)
+}
+
+fn test_passive_node_crash(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut timeline = MembersTimeline::new();
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
     }
-    assume!(scaling_ok, "Bad network load scaling")?;
-    assume!(load_ratio_ok, "Bad max/min node load")?;
-    Ok(true)
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+
+    // node crashes
+    let crashed_node = group.remove(rand.gen_range(0..group.len()));
+    sys.crash_node(&crashed_node);
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+    check_no_flapping(&mut sys, &mut timeline, group.into_iter().collect(), 60.)
+}
+
+fn test_passive_node_offline_recover(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut timeline = MembersTimeline::new();
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+
+    // node goes offline
+    let offline_node = group.remove(rand.gen_range(0..group.len()));
+    sys.disconnect_node(&offline_node);
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+
+    // node goes back online
+    sys.connect_node(&offline_node);
+    group.push(offline_node);
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+    check_no_flapping(&mut sys, &mut timeline, group.into_iter().collect(), 60.)
+}
+
+fn test_passive_flaky_network(config: &TestConfig) -> TestResult {
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut sys = build_system(config);
+    let mut timeline = MembersTimeline::new();
+    let mut group = sys.get_node_ids();
+    group.shuffle(&mut rand);
+    let seed = &group.first().unwrap();
+
+    for node in &group {
+        sys.send_local(JsonMessage::from("JOIN", &JoinMessage { seed }), node);
+    }
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+
+    // make network unreliable for a while
+    sys.set_drop_rate(0.5);
+    observe_for_duration(&mut sys, &mut timeline, 30.);
+    sys.set_drop_rate(0.0);
+    observe_until_stabilized(&mut sys, &mut timeline, group.clone().into_iter().collect())?;
+    check_no_flapping(&mut sys, &mut timeline, group.into_iter().collect(), 60.)
 }
 
 // CLI -----------------------------------------------------------------------------------------------------------------
@@ -718,6 +1977,51 @@ struct Args {
     #[clap(long, short, default_value = "100")]
     monkeys: u32,
 
+    /// Rate of membership changes in churn test (events per second)
+    #[clap(long, default_value = "0.1")]
+    churn_rate: f64,
+
+    /// Period of link flapping (seconds up, then seconds down)
+    #[clap(long, default_value = "1.0")]
+    flap_period: f64,
+
+    /// Run tests with clocks drifting with the given max rate (e.g. 0.1 for 10%)
+    #[clap(long)]
+    clock_drift: Option<f64>,
+
+    /// Directory to write scalability tests results as CSV (optional)
+    #[clap(long)]
+    csv_dir: Option<String>,
+
+    /// Run tests observing members lists passively (nodes should emit MEMBERS on each change)
+    #[clap(long, short)]
+    passive: bool,
+
+    /// Fraction of nodes running the reference SWIM implementation instead of the solution
+    /// (use 1.0 to run tests against the reference implementation only)
+    #[clap(long, default_value = "0.0")]
+    reference_ratio: f64,
+
+    /// Also run scalability tests against the reference implementation and print its results for comparison
+    #[clap(long)]
+    compare_reference: bool,
+
+    /// Run tests with quick node restarts and false suspicions
+    #[clap(long)]
+    crash_restart: bool,
+
+    /// Run test with continuous joins, leaves, crashes and restarts of nodes
+    #[clap(long)]
+    churn: bool,
+
+    /// Run tests with flapping and asymmetric links between nodes
+    #[clap(long)]
+    link_faults: bool,
+
+    /// Run tests with merging of groups formed in long-lasting network partitions
+    #[clap(long)]
+    split_brain: bool,
+
     /// Path to dslib directory
     #[clap(long = "lib", short = 'l', default_value = "../../dslib")]
     dslib_path: String,
@@ -737,6 +2041,14 @@ fn main() {
         node_factory: &node_factory,
         node_count: args.node_count,
         seed: args.seed,
+        churn_rate: args.churn_rate,
+        flap_period: args.flap_period,
+        clock_drift: args.clock_drift.unwrap_or(0.),
+        csv_dir: args.csv_dir,
+        passive: args.passive,
+        reference_ratio: args.reference_ratio,
+        compare_reference: args.compare_reference,
+        debug: args.debug,
     };
     let mut tests = TestSuite::new();
 
@@ -803,6 +2115,75 @@ fn main() {
         config.clone(),
     );
     tests.add("SCALABILITY CRASH", test_scalability_crash, config.clone());
+    if args.passive {
+        tests.add("PASSIVE SIMPLE", test_passive_simple, config.clone());
+        tests.add(
+            "PASSIVE NODE CRASH",
+            test_passive_node_crash,
+            config.clone(),
+        );
+        tests.add(
+            "PASSIVE NODE OFFLINE RECOVER",
+            test_passive_node_offline_recover,
+            config.clone(),
+        );
+        tests.add(
+            "PASSIVE FLAKY NETWORK",
+            test_passive_flaky_network,
+            config.clone(),
+        );
+    }
+    if args.crash_restart {
+        tests.add(
+            "NODE CRASH RESTART",
+            test_node_crash_restart,
+            config.clone(),
+        );
+        tests.add(
+            "NODE CRASH RESTART REPEATED",
+            test_node_crash_restart_repeated,
+            config.clone(),
+        );
+        tests.add(
+            "SEED NODE CRASH RESTART",
+            test_seed_node_crash_restart,
+            config.clone(),
+        );
+        tests.add(
+            "NODE FALSE SUSPICION",
+            test_node_false_suspicion,
+            config.clone(),
+        );
+    }
+    if args.churn {
+        tests.add("CHURN", test_churn, config.clone());
+    }
+    if args.link_faults {
+        tests.add("FLAPPING LINK", test_flapping_link, config.clone());
+        tests.add("FLAPPING NODE", test_flapping_node, config.clone());
+        tests.add("ASYMMETRIC LINKS", test_asymmetric_links, config.clone());
+        tests.add(
+            "FLAPPING AND ASYMMETRIC LINKS",
+            test_flapping_and_asymmetric_links,
+            config.clone(),
+        );
+    }
+    if args.split_brain {
+        tests.add("SPLIT BRAIN MERGE", test_split_brain_merge, config.clone());
+        tests.add(
+            "SPLIT BRAIN MERGE WITH JOINS",
+            test_split_brain_merge_with_joins,
+            config.clone(),
+        );
+    }
+    if args.clock_drift.is_some() {
+        tests.add("CLOCK DRIFT", test_clock_drift, config.clone());
+        tests.add(
+            "CLOCK DRIFT NODE CRASH",
+            test_clock_drift_node_crash,
+            config.clone(),
+        );
+    }
 
     if args.test.is_none() {
         tests.run();
@@ -0,0 +1,593 @@
+// Reference implementation of group membership based on SWIM protocol
+// (Das et al., "SWIM: Scalable Weakly-consistent Infection-style Process Group Membership Protocol").
+//
+// The node speaks the same local protocol as solution.py (JOIN, LEAVE, GET_MEMBERS -> MEMBERS),
+// so it can be used in place of Python nodes or mixed with them in a single group.
+// In the latter case the Python solution should implement the wire protocol described below.
+//
+// Messages between nodes:
+// - PING {seq, updates}: direct probe, answered with ACK carrying the same seq
+// - ACK {seq, updates}: probe response
+// - PING_REQ {seq, target, updates}: asks to probe target and forward its ACK with the given seq
+// - SYNC {members, reply}: full state exchange used on join and when contacting nodes considered dead,
+//   the receiver answers with its own state if reply is true
+//
+// Each update is {id, incarnation, status} where status is one of alive, suspect, dead, left.
+// Updates are disseminated by piggybacking them on PING, ACK and PING_REQ messages.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+use rand::prelude::*;
+use rand_pcg::Pcg64;
+use serde::{Deserialize, Serialize};
+
+use dslib::node::{Context, Node};
+use dslib::pynode::JsonMessage;
+
+const PROTOCOL_PERIOD: f64 = 1.;
+const PING_TIMEOUT: f64 = 0.3;
+const PING_REQ_COUNT: usize = 3;
+const SUSPECT_TIMEOUT: f64 = 5.;
+const JOIN_RETRY_INTERVAL: f64 = 1.;
+const RECONNECT_PERIODS: u32 = 5;
+const MAX_PIGGYBACK: usize = 8;
+const RETRANSMIT_MULT: u32 = 3;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Alive,
+    Suspect,
+    Dead,
+    Left,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Update {
+    id: String,
+    incarnation: u64,
+    status: Status,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PingMessage {
+    seq: u64,
+    updates: Vec<Update>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PingReqMessage {
+    seq: u64,
+    target: String,
+    updates: Vec<Update>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SyncMessage {
+    members: Vec<Update>,
+    reply: bool,
+}
+
+#[derive(Deserialize)]
+struct JoinMessage {
+    seed: String,
+}
+
+#[derive(Serialize)]
+struct MembersMessage {
+    members: Vec<String>,
+}
+
+struct Member {
+    incarnation: u64,
+    status: Status,
+}
+
+struct Probe {
+    seq: u64,
+    target: String,
+    acked: bool,
+}
+
+pub struct SwimNode {
+    id: String,
+    joined: bool,
+    synced: bool,
+    seed: String,
+    incarnation: u64,
+    // BTreeMap is used to keep iteration order (and thus the simulation) deterministic
+    members: BTreeMap<String, Member>,
+    broadcasts: Vec<(Update, u32)>,
+    probe: Option<Probe>,
+    probe_order: Vec<String>,
+    probe_timer_started: bool,
+    period: u32,
+    next_seq: u64,
+    // seq of ping sent on behalf of other node -> (requester, requester seq)
+    relays: HashMap<u64, (String, u64)>,
+    last_sync: HashMap<String, f64>,
+    notify_changes: bool,
+    rand: Pcg64,
+}
+
+impl SwimNode {
+    pub fn new(id: &str, seed: u64, notify_changes: bool) -> Self {
+        let id_hash = id
+            .bytes()
+            .fold(0u64, |h, b| h.wrapping_mul(31).wrapping_add(b as u64));
+        Self {
+            id: id.to_string(),
+            joined: false,
+            synced: false,
+            seed: String::new(),
+            incarnation: 0,
+            members: BTreeMap::new(),
+            broadcasts: Vec::new(),
+            probe: None,
+            probe_order: Vec::new(),
+            probe_timer_started: false,
+            period: 0,
+            next_seq: 1,
+            relays: HashMap::new(),
+            last_sync: HashMap::new(),
+            notify_changes,
+            rand: Pcg64::seed_from_u64(seed ^ id_hash),
+        }
+    }
+
+    fn group(&self) -> Vec<String> {
+        let mut group: Vec<String> = self
+            .members
+            .iter()
+            .filter(|(_, m)| m.status == Status::Alive || m.status == Status::Suspect)
+            .map(|(id, _)| id.clone())
+            .collect();
+        if self.joined {
+            group.push(self.id.clone());
+        }
+        group
+    }
+
+    fn self_update(&self) -> Update {
+        Update {
+            id: self.id.clone(),
+            incarnation: self.incarnation,
+            status: if self.joined {
+                Status::Alive
+            } else {
+                Status::Left
+            },
+        }
+    }
+
+    fn full_state(&self) -> Vec<Update> {
+        let mut state = vec![self.self_update()];
+        for (id, member) in self.members.iter() {
+            state.push(Update {
+                id: id.clone(),
+                incarnation: member.incarnation,
+                status: member.status,
+            });
+        }
+        state
+    }
+
+    fn enqueue_broadcast(&mut self, update: Update) {
+        self.broadcasts.retain(|(u, _)| u.id != update.id);
+        let group_size = self.group().len().max(1) as f64;
+        let retransmits = RETRANSMIT_MULT * (group_size.log2().ceil() as u32 + 1);
+        self.broadcasts.push((update, retransmits));
+    }
+
+    fn take_updates(&mut self) -> Vec<Update> {
+        // most recent updates have the most remaining retransmissions
+        self.broadcasts
+            .sort_by_key(|(_, remaining)| Reverse(*remaining));
+        let mut updates = Vec::new();
+        for (update, remaining) in self.broadcasts.iter_mut().take(MAX_PIGGYBACK) {
+            updates.push(update.clone());
+            *remaining -= 1;
+        }
+        self.broadcasts.retain(|(_, remaining)| *remaining > 0);
+        updates
+    }
+
+    fn random_members(&mut self, status: Status, exclude: &str, count: usize) -> Vec<String> {
+        let candidates: Vec<String> = self
+            .members
+            .iter()
+            .filter(|(id, m)| m.status == status && *id != exclude)
+            .map(|(id, _)| id.clone())
+            .collect();
+        candidates
+            .choose_multiple(&mut self.rand, count)
+            .cloned()
+            .collect()
+    }
+
+    fn refute(&mut self, incarnation: u64) {
+        self.incarnation = self.incarnation.max(incarnation + 1);
+        let update = self.self_update();
+        self.enqueue_broadcast(update);
+    }
+
+    fn set_status(
+        &mut self,
+        id: &str,
+        incarnation: u64,
+        status: Status,
+        broadcast: bool,
+        ctx: &mut Context<JsonMessage>,
+    ) {
+        let group_before = self.group().len();
+        self.members.insert(
+            id.to_string(),
+            Member {
+                incarnation,
+                status,
+            },
+        );
+        if status == Status::Suspect {
+            ctx.set_timer(&format!("suspect:{}:{}", id, incarnation), SUSPECT_TIMEOUT);
+        }
+        if broadcast {
+            self.enqueue_broadcast(Update {
+                id: id.to_string(),
+                incarnation,
+                status,
+            });
+        }
+        if self.notify_changes && self.group().len() != group_before {
+            self.send_members(ctx);
+        }
+    }
+
+    // Applies update received from other node, dead and suspect statuses from full state syncs
+    // are only used for unknown members to avoid false positives after partition healing.
+    // Members first learned from full state are not gossiped, since the sender already knows them.
+    fn apply_update(&mut self, update: Update, from_sync: bool, ctx: &mut Context<JsonMessage>) {
+        if update.id == self.id {
+            if self.joined {
+                let refute = match update.status {
+                    Status::Alive => update.incarnation > self.incarnation,
+                    _ => update.incarnation >= self.incarnation,
+                };
+                if refute {
+                    self.refute(update.incarnation);
+                }
+            }
+            return;
+        }
+        let apply = match self.members.get(&update.id) {
+            None => true,
+            Some(member) => match update.status {
+                Status::Alive => update.incarnation > member.incarnation,
+                Status::Suspect => {
+                    !from_sync
+                        && ((member.status == Status::Alive
+                            && update.incarnation >= member.incarnation)
+                            || (member.status == Status::Suspect
+                                && update.incarnation > member.incarnation))
+                }
+                Status::Dead => {
+                    !from_sync
+                        && (member.status == Status::Alive || member.status == Status::Suspect)
+                        && update.incarnation >= member.incarnation
+                }
+                Status::Left => {
+                    member.status != Status::Left && update.incarnation >= member.incarnation
+                }
+            },
+        };
+        if apply {
+            let broadcast = !from_sync || self.members.contains_key(&update.id);
+            self.set_status(
+                &update.id,
+                update.incarnation,
+                update.status,
+                broadcast,
+                ctx,
+            );
+        }
+    }
+
+    fn send_members(&self, ctx: &mut Context<JsonMessage>) {
+        let mut members = self.group();
+        members.sort();
+        ctx.send_local(JsonMessage::from("MEMBERS", &MembersMessage { members }));
+    }
+
+    fn send_ping(&mut self, seq: u64, to: &str, ctx: &mut Context<JsonMessage>) {
+        let updates = self.take_updates();
+        ctx.send(JsonMessage::from("PING", &PingMessage { seq, updates }), to);
+    }
+
+    fn send_ack(&mut self, seq: u64, to: &str, ctx: &mut Context<JsonMessage>) {
+        let mut updates = self.take_updates();
+        // let the node we consider dead know it, so it can refute
+        if let Some(member) = self.members.get(to) {
+            if member.status == Status::Dead {
+                updates.insert(
+                    0,
+                    Update {
+                        id: to.to_string(),
+                        incarnation: member.incarnation,
+                        status: Status::Dead,
+                    },
+                );
+            }
+        }
+        ctx.send(JsonMessage::from("ACK", &PingMessage { seq, updates }), to);
+    }
+
+    fn send_sync(&mut self, to: &str, reply: bool, ctx: &mut Context<JsonMessage>) {
+        self.last_sync.insert(to.to_string(), ctx.time());
+        let members = self.full_state();
+        ctx.send(
+            JsonMessage::from("SYNC", &SyncMessage { members, reply }),
+            to,
+        );
+    }
+
+    // Nodes considered dead may be alive on the other side of a healed partition,
+    // so exchange full states with them and with unknown nodes (at most once per reconnect interval)
+    fn maybe_reconnect(&mut self, from: &str, ctx: &mut Context<JsonMessage>) {
+        let is_known = matches!(self.members.get(from), Some(m) if m.status != Status::Dead);
+        if is_known {
+            return;
+        }
+        let interval = PROTOCOL_PERIOD * RECONNECT_PERIODS as f64;
+        let recently_synced = match self.last_sync.get(from) {
+            Some(time) => ctx.time() - time < interval,
+            None => false,
+        };
+        if !recently_synced {
+            self.send_sync(from, true, ctx);
+        }
+    }
+
+    fn next_probe_target(&mut self) -> Option<String> {
+        loop {
+            if self.probe_order.is_empty() {
+                self.probe_order = self
+                    .members
+                    .iter()
+                    .filter(|(_, m)| m.status == Status::Alive || m.status == Status::Suspect)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                if self.probe_order.is_empty() {
+                    return None;
+                }
+                self.probe_order.shuffle(&mut self.rand);
+            }
+            let target = self.probe_order.pop().unwrap();
+            match self.members.get(&target) {
+                Some(m) if m.status == Status::Alive || m.status == Status::Suspect => {
+                    return Some(target)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn on_protocol_period(&mut self, ctx: &mut Context<JsonMessage>) {
+        if !self.joined {
+            self.probe_timer_started = false;
+            return;
+        }
+        ctx.set_timer("probe", PROTOCOL_PERIOD);
+        // finish previous probe
+        if let Some(probe) = self.probe.take() {
+            if !probe.acked {
+                if let Some(member) = self.members.get(&probe.target) {
+                    if member.status == Status::Alive {
+                        let incarnation = member.incarnation;
+                        self.set_status(&probe.target, incarnation, Status::Suspect, true, ctx);
+                    }
+                }
+            }
+        }
+        // start new probe
+        if let Some(target) = self.next_probe_target() {
+            let seq = self.next_seq;
+            self.next_seq += 1;
+            self.send_ping(seq, &target, ctx);
+            self.probe = Some(Probe {
+                seq,
+                target,
+                acked: false,
+            });
+            ctx.set_timer(&format!("ping_timeout:{}", seq), PING_TIMEOUT);
+        }
+        // try to reach one of the dead members
+        self.period = (self.period + 1) % RECONNECT_PERIODS;
+        if self.period == 0 {
+            if let Some(target) = self.random_members(Status::Dead, "", 1).pop() {
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                self.send_ping(seq, &target, ctx);
+            }
+        }
+    }
+
+    fn on_ping_timeout(&mut self, seq: u64, ctx: &mut Context<JsonMessage>) {
+        let target = match &self.probe {
+            Some(probe) if probe.seq == seq && !probe.acked => probe.target.clone(),
+            _ => return,
+        };
+        for helper in self.random_members(Status::Alive, &target, PING_REQ_COUNT) {
+            let updates = self.take_updates();
+            ctx.send(
+                JsonMessage::from(
+                    "PING_REQ",
+                    &PingReqMessage {
+                        seq,
+                        target: target.clone(),
+                        updates,
+                    },
+                ),
+                &helper,
+            );
+        }
+    }
+
+    fn join(&mut self, seed: String, ctx: &mut Context<JsonMessage>) {
+        if self.joined {
+            return;
+        }
+        self.joined = true;
+        // make sure the previous leave is overridden
+        self.incarnation += 1;
+        self.seed = seed;
+        self.synced = self.seed == self.id;
+        if !self.synced {
+            let seed = self.seed.clone();
+            self.send_sync(&seed, true, ctx);
+            ctx.set_timer("join", JOIN_RETRY_INTERVAL);
+        }
+        if !self.probe_timer_started {
+            self.probe_timer_started = true;
+            ctx.set_timer("probe", PROTOCOL_PERIOD);
+        }
+        if self.notify_changes {
+            self.send_members(ctx);
+        }
+    }
+
+    fn leave(&mut self, ctx: &mut Context<JsonMessage>) {
+        if !self.joined {
+            return;
+        }
+        self.joined = false;
+        self.probe = None;
+        let members = vec![self.self_update()];
+        for (id, member) in self.members.iter() {
+            if member.status == Status::Alive || member.status == Status::Suspect {
+                ctx.send(
+                    JsonMessage::from(
+                        "SYNC",
+                        &SyncMessage {
+                            members: members.clone(),
+                            reply: false,
+                        },
+                    ),
+                    id,
+                );
+            }
+        }
+        self.members.clear();
+        self.broadcasts.clear();
+        self.probe_order.clear();
+        self.relays.clear();
+    }
+}
+
+impl Node<JsonMessage> for SwimNode {
+    fn id(&self) -> &String {
+        &self.id
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        if !self.joined {
+            return;
+        }
+        match msg.tip.as_str() {
+            "PING" => {
+                let data: PingMessage = serde_json::from_str(&msg.data).unwrap();
+                for update in data.updates {
+                    self.apply_update(update, false, ctx);
+                }
+                self.send_ack(data.seq, &from, ctx);
+            }
+            "ACK" => {
+                let data: PingMessage = serde_json::from_str(&msg.data).unwrap();
+                for update in data.updates {
+                    self.apply_update(update, false, ctx);
+                }
+                if let Some((requester, seq)) = self.relays.remove(&data.seq) {
+                    let updates = self.take_updates();
+                    ctx.send(
+                        JsonMessage::from("ACK", &PingMessage { seq, updates }),
+                        &requester,
+                    );
+                } else if let Some(probe) = &mut self.probe {
+                    if probe.seq == data.seq {
+                        probe.acked = true;
+                    }
+                }
+            }
+            "PING_REQ" => {
+                let data: PingReqMessage = serde_json::from_str(&msg.data).unwrap();
+                for update in data.updates {
+                    self.apply_update(update, false, ctx);
+                }
+                let seq = self.next_seq;
+                self.next_seq += 1;
+                self.relays.insert(seq, (from.clone(), data.seq));
+                self.send_ping(seq, &data.target, ctx);
+            }
+            "SYNC" => {
+                let data: SyncMessage = serde_json::from_str(&msg.data).unwrap();
+                for update in data.members {
+                    // sender state is as reliable as the piggybacked updates
+                    let from_sync = update.id != from;
+                    self.apply_update(update, from_sync, ctx);
+                }
+                if from == self.seed {
+                    self.synced = true;
+                }
+                if data.reply {
+                    self.send_sync(&from, false, ctx);
+                }
+                return;
+            }
+            _ => {}
+        }
+        self.maybe_reconnect(&from, ctx);
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        match msg.tip.as_str() {
+            "JOIN" => {
+                let data: JoinMessage = serde_json::from_str(&msg.data).unwrap();
+                self.join(data.seed, ctx);
+            }
+            "LEAVE" => self.leave(ctx),
+            "GET_MEMBERS" => self.send_members(ctx),
+            _ => {}
+        }
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        let parts: Vec<&str> = timer.split(':').collect();
+        match parts[0] {
+            "probe" => self.on_protocol_period(ctx),
+            "ping_timeout" if self.joined => {
+                self.on_ping_timeout(parts[1].parse().unwrap(), ctx);
+            }
+            "suspect" => {
+                let id = parts[1].to_string();
+                let incarnation: u64 = parts[2].parse().unwrap();
+                let expired = matches!(
+                    self.members.get(&id),
+                    Some(m) if m.status == Status::Suspect && m.incarnation == incarnation
+                );
+                if self.joined && expired {
+                    self.set_status(&id, incarnation, Status::Dead, true, ctx);
+                }
+            }
+            "join" if self.joined && !self.synced => {
+                let seed = self.seed.clone();
+                self.send_sync(&seed, true, ctx);
+                ctx.set_timer("join", JOIN_RETRY_INTERVAL);
+            }
+            _ => {}
+        }
+    }
+
+    fn max_size(&mut self) -> u64 {
+        // rough estimate of the state size dominated by the members list
+        self.members.keys().map(|id| id.len() as u64 + 16).sum()
+    }
+}
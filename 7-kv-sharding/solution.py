@@ -1,11 +1,15 @@
 from dslib import Context, Message, Node
-from typing import List
+from typing import List, Optional
 
 
 class StorageNode(Node):
-    def __init__(self, node_id: str, nodes: List[str]):
+    # In STORAGE tests the node also gets a path to its own directory for durable storage.
+    # Files written there are preserved when the node is crashed and restarted with the same id,
+    # so the node should restore its data from this directory on startup.
+    def __init__(self, node_id: str, nodes: List[str], storage_dir: Optional[str] = None):
         self._id = node_id
         self._nodes = set(nodes)
+        self._storage_dir = storage_dir
         self._data = {}
 
     def on_local_message(self, msg: Message, ctx: Context):
@@ -27,6 +31,10 @@ class StorageNode(Node):
         # Store (key, value) record
         # Request:
         #   PUT {"key": "some key", "value: "some value"}
+        # Optional "ttl" field sets record lifetime in seconds of simulated time,
+        # the record should be removed (and not returned by any request) after it expires,
+        # PUT without "ttl" makes the record persistent:
+        #   PUT {"key": "some key", "value: "some value", "ttl": 10.0}
         # Response:
         #   PUT_RESP {"key": "some key", "value: "some value"}
         elif msg.type == 'PUT':
@@ -53,9 +61,68 @@ class StorageNode(Node):
             })
             ctx.send_local(resp)
 
+        # Compare-and-swap: set new value only if the current value equals expected
+        # Request:
+        #   CAS {"key": "some key", "expected": "old value", "new": "new value"}
+        #   CAS {"key": "some key", "expected": null, "new": "new value"} - set only if record is not found
+        # Response:
+        #   CAS_RESP {"key": "some key", "success": true, "value": "new value"}
+        #   CAS_RESP {"key": "some key", "success": false, "value": "current value"} - value is null if not found
+        elif msg.type == 'CAS':
+            key = msg['key']
+            success = self._data.get(key) == msg['expected']
+            if success:
+                self._data[key] = msg['new']
+            resp = Message('CAS_RESP', {
+                'key': key,
+                'success': success,
+                'value': self._data.get(key)
+            })
+            ctx.send_local(resp)
+
+        # Get values for multiple keys
+        # Request:
+        #   MULTI_GET {"keys": ["key1", "key2", ...]}
+        # Response:
+        #   MULTI_GET_RESP {"records": {"key1": "value1", "key2": null, ...}} - null if record is not found
+        elif msg.type == 'MULTI_GET':
+            resp = Message('MULTI_GET_RESP', {
+                'records': {k: self._data.get(k) for k in msg['keys']}
+            })
+            ctx.send_local(resp)
+
+        # Store multiple records
+        # Request:
+        #   MULTI_PUT {"records": {"key1": "value1", "key2": "value2", ...}}
+        # Response:
+        #   MULTI_PUT_RESP {"records": {"key1": "value1", "key2": "value2", ...}}
+        elif msg.type == 'MULTI_PUT':
+            records = msg['records']
+            self._data.update(records)
+            resp = Message('MULTI_PUT_RESP', {
+                'records': records
+            })
+            ctx.send_local(resp)
+
+        # Get records with keys starting with prefix in sorted order (at most limit records)
+        # Request:
+        #   SCAN {"prefix": "some prefix", "limit": 10}
+        # Response:
+        #   SCAN_RESP {"prefix": "some prefix", "records": [{"key": "some key", "value": "some value"}, ...]}
+        elif msg.type == 'SCAN':
+            prefix = msg['prefix']
+            keys = sorted(k for k in self._data if k.startswith(prefix))[:msg['limit']]
+            resp = Message('SCAN_RESP', {
+                'prefix': prefix,
+                'records': [{'key': k, 'value': self._data[k]} for k in keys]
+            })
+            ctx.send_local(resp)
+
         # Notification that a new node is added to the system.
         # Request:
         #   NODE_ADDED {"id": "node id"}
+        #   NODE_ADDED {"id": "node id", "weight": 2.0} - node with capacity weight (1.0 by default),
+        #                                                 for already added node changes its weight
         # Response:
         #   N/A
         elif msg.type == 'NODE_ADDED':
@@ -80,6 +147,24 @@ class StorageNode(Node):
             })
             ctx.send_local(resp)
 
+        # Get keys placement scheme used by the node (optional, used in tests with --oracle flag)
+        # Request:
+        #   PLACEMENT {}
+        # Response:
+        #   PLACEMENT_RESP {"scheme": "consistent_hashing", "vnodes": 100}
+        #   PLACEMENT_RESP {"scheme": "rendezvous"}
+        # Hash H(s) is the first 8 bytes of MD5 of s as little-endian integer:
+        #   int.from_bytes(hashlib.md5(s.encode()).digest()[:8], 'little')
+        # consistent_hashing - node has vnodes points H(f"{node_id}#{i}") for i in range(vnodes) on the ring,
+        #   key is stored on the node with the first point >= H(key) (or the smallest point if there is none)
+        # rendezvous - key is stored on the node with the max H(f"{node_id}#{key}")
+        # Ties are broken in favor of node with the smaller id (ids are compared as numbers).
+        elif msg.type == 'PLACEMENT':
+            resp = Message('PLACEMENT_RESP', {
+                'scheme': 'custom'
+            })
+            ctx.send_local(resp)
+
         # Get keys of records stored on the node
         # Request:
         #   DUMP_KEYS {}
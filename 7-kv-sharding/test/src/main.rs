@@ -1,8 +1,12 @@
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
+use std::fs;
 use std::io::Write;
+use std::path::PathBuf;
 
 use assertables::{assume, assume_eq};
+use byteorder::{ByteOrder, LittleEndian};
 use clap::Parser;
 use decorum::R64;
 use env_logger::Builder;
@@ -13,7 +17,8 @@ use rand_pcg::Pcg64;
 use serde::{Deserialize, Serialize};
 use sugars::{rc, refcell};
 
-use dslib::pynode::{JsonMessage, PyNodeFactory};
+use dslib::node::{Context, Node};
+use dslib::pynode::{JsonMessage, PyNode, PyNodeFactory};
 use dslib::system::System;
 use dslib::test::{TestResult, TestSuite};
 
@@ -34,6 +39,8 @@ struct GetRespMessage<'a> {
 struct PutMessage<'a> {
     key: &'a str,
     value: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ttl: Option<f64>,
 }
 
 #[derive(Deserialize)]
@@ -53,6 +60,58 @@ struct DeleteRespMessage<'a> {
     value: Option<&'a str>,
 }
 
+#[derive(Serialize)]
+struct CasMessage<'a> {
+    key: &'a str,
+    expected: Option<&'a str>,
+    new: &'a str,
+}
+
+#[derive(Deserialize)]
+struct CasRespMessage<'a> {
+    key: &'a str,
+    success: bool,
+    value: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct MultiGetMessage<'a> {
+    keys: &'a [String],
+}
+
+#[derive(Deserialize)]
+struct MultiGetRespMessage {
+    records: BTreeMap<String, Option<String>>,
+}
+
+#[derive(Serialize)]
+struct MultiPutMessage<'a> {
+    records: &'a BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct MultiPutRespMessage {
+    records: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+struct ScanMessage<'a> {
+    prefix: &'a str,
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+struct ScanRecord {
+    key: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ScanRespMessage<'a> {
+    prefix: &'a str,
+    records: Vec<ScanRecord>,
+}
+
 #[derive(Serialize)]
 struct DumpKeysMessage {}
 
@@ -69,9 +128,20 @@ struct CountRecordsRespMessage {
     count: u64,
 }
 
+#[derive(Serialize)]
+struct PlacementMessage {}
+
+#[derive(Deserialize)]
+struct PlacementRespMessage {
+    scheme: String,
+    vnodes: Option<u32>,
+}
+
 #[derive(Serialize)]
 struct NodeAddedMessage<'a> {
     id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<f64>,
 }
 
 #[derive(Serialize)]
@@ -86,6 +156,9 @@ struct TestConfig<'a> {
     node_factory: &'a PyNodeFactory,
     node_count: u32,
     seed: u64,
+    zipf_exponent: f64,
+    trace_hops: bool,
+    with_storage: bool,
 }
 
 fn init_logger(level: LevelFilter) {
@@ -96,6 +169,10 @@ fn init_logger(level: LevelFilter) {
 }
 
 fn build_system(config: &TestConfig, measure_max_size: bool) -> System<JsonMessage> {
+    if config.with_storage {
+        clear_storage();
+    }
+    reset_hop_tracing();
     let mut sys = System::with_seed(config.seed);
     sys.set_delays(0.01, 0.1);
     let mut node_ids = Vec::new();
@@ -103,27 +180,119 @@ fn build_system(config: &TestConfig, measure_max_size: bool) -> System<JsonMessa
         node_ids.push(format!("{}", n));
     }
     for node_id in node_ids.iter() {
-        let mut node = config
-            .node_factory
-            .build(node_id, (node_id, node_ids.clone()), config.seed);
+        let mut node = build_node(node_id, &node_ids, config);
         if measure_max_size {
             node.set_max_size_freq(1000000);
         }
-        sys.add_node(rc!(refcell!(node)));
+        add_to_system(&mut sys, node, config);
     }
     return sys;
 }
 
-fn add_node(node_id: &str, sys: &mut System<JsonMessage>, config: &TestConfig) {
-    let mut node_ids = Vec::new();
-    for id in sys.get_node_ids() {
-        node_ids.push(id);
+fn add_to_system(sys: &mut System<JsonMessage>, node: PyNode, config: &TestConfig) {
+    if config.trace_hops {
+        sys.add_node(rc!(refcell!(HopTracer::new(node))));
+    } else {
+        sys.add_node(rc!(refcell!(node)));
     }
+}
+
+fn add_node(node_id: &str, sys: &mut System<JsonMessage>, config: &TestConfig) {
+    let node_ids = sys.get_node_ids();
+    add_node_with_members(node_id, &node_ids, sys, config);
+}
+
+// Added node is initialized with the given members (without removed nodes still present in the system)
+fn add_node_with_members(
+    node_id: &str,
+    members: &[String],
+    sys: &mut System<JsonMessage>,
+    config: &TestConfig,
+) {
+    let mut node_ids = members.to_vec();
     node_ids.push(node_id.to_string());
-    let node = config
-        .node_factory
-        .build(node_id, (node_id, node_ids), config.seed);
-    sys.add_node(rc!(refcell!(node)));
+    let node = build_node(node_id, &node_ids, config);
+    add_to_system(sys, node, config);
+}
+
+// Each node gets its own directory for durable storage which is preserved when the node is restarted
+fn storage_root() -> PathBuf {
+    env::temp_dir().join(format!("kv-sharding-storage-{}", std::process::id()))
+}
+
+fn clear_storage() {
+    let _ = fs::remove_dir_all(storage_root());
+}
+
+// Removes storage directories when a storage test returns (including on failure)
+struct StorageCleanup;
+
+impl Drop for StorageCleanup {
+    fn drop(&mut self) {
+        clear_storage();
+    }
+}
+
+// Node is initialized with the given node ids (including itself)
+// and, if storage is used, with the path to its storage directory
+fn build_node(node_id: &str, node_ids: &[String], config: &TestConfig) -> PyNode {
+    if config.with_storage {
+        let storage_dir = storage_root().join(node_id);
+        fs::create_dir_all(&storage_dir).unwrap();
+        config.node_factory.build(
+            node_id,
+            (
+                node_id,
+                node_ids.to_vec(),
+                storage_dir.to_string_lossy().to_string(),
+            ),
+            config.seed,
+        )
+    } else {
+        config
+            .node_factory
+            .build(node_id, (node_id, node_ids.to_vec()), config.seed)
+    }
+}
+
+// Crashes the given nodes at once and then rebuilds them from their storage
+fn restart_nodes(
+    restarted: &[String],
+    node_ids: &[String],
+    sys: &mut System<JsonMessage>,
+    config: &TestConfig,
+) {
+    for node in restarted.iter() {
+        sys.crash_node(node);
+    }
+    for node in restarted.iter() {
+        let rebuilt = build_node(node, node_ids, config);
+        add_to_system(sys, rebuilt, config);
+    }
+}
+
+// Announces node additions (true) and removals (false) in the given order making the given number of steps
+// between them, so that the next change can happen before the previous rebalancing is finished
+fn apply_membership_changes(
+    sys: &mut System<JsonMessage>,
+    config: &TestConfig,
+    nodes: &mut Vec<String>,
+    changes: &[(String, bool)],
+    steps_between: u32,
+) {
+    for (i, (node, added)) in changes.iter().enumerate() {
+        if i > 0 && steps_between > 0 {
+            sys.steps(steps_between);
+        }
+        if *added {
+            add_node_with_members(node, nodes, sys, config);
+            send_node_added(sys, node);
+            nodes.push(node.clone());
+        } else {
+            send_node_removed(sys, node);
+            nodes.retain(|x| x != node);
+        }
+    }
 }
 
 fn check_get(
@@ -152,7 +321,21 @@ fn check_put(
     value: &str,
     max_steps: u32,
 ) -> TestResult {
-    sys.send_local(JsonMessage::from("PUT", &PutMessage { key, value }), node);
+    check_put_with_ttl(sys, node, key, value, None, max_steps)
+}
+
+fn check_put_with_ttl(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    key: &str,
+    value: &str,
+    ttl: Option<f64>,
+    max_steps: u32,
+) -> TestResult {
+    sys.send_local(
+        JsonMessage::from("PUT", &PutMessage { key, value, ttl }),
+        node,
+    );
     let res = sys.step_until_local_message_max_steps(node, max_steps);
     assume!(res.is_ok(), format!("PUT_RESP is not returned by {}", node))?;
     let msgs = res.unwrap();
@@ -186,6 +369,221 @@ fn check_delete(
     Ok(true)
 }
 
+fn check_cas(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    key: &str,
+    expected: Option<&str>,
+    new: &str,
+    result: (bool, Option<&str>),
+    max_steps: u32,
+) -> TestResult {
+    sys.send_local(
+        JsonMessage::from("CAS", &CasMessage { key, expected, new }),
+        node,
+    );
+    let res = sys.step_until_local_message_max_steps(node, max_steps);
+    assume!(res.is_ok(), format!("CAS_RESP is not returned by {}", node))?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "CAS_RESP")?;
+    let data: CasRespMessage = serde_json::from_str(&msg.data).unwrap();
+    assume_eq!(data.key, key)?;
+    assume_eq!(data.success, result.0)?;
+    assume_eq!(data.value, result.1)?;
+    Ok(true)
+}
+
+// Sends CAS with the same expected value and different new values from all nodes at once,
+// checks that exactly one of them succeeds and returns the winning value
+fn run_concurrent_cas(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+    key: &str,
+    expected: Option<&str>,
+    rand: &mut Pcg64,
+) -> Result<String, String> {
+    let mut new_values = Vec::new();
+    for node in nodes.iter() {
+        let new = random_string(8, rand);
+        sys.send_local(
+            JsonMessage::from(
+                "CAS",
+                &CasMessage {
+                    key,
+                    expected,
+                    new: &new,
+                },
+            ),
+            node,
+        );
+        new_values.push(new);
+    }
+    let mut winners = Vec::new();
+    let mut observed = Vec::new();
+    for (node, new) in nodes.iter().zip(new_values.iter()) {
+        let res = sys.step_until_local_message_max_steps(node, 1000);
+        assume!(res.is_ok(), format!("CAS_RESP is not returned by {}", node))?;
+        let msgs = res.unwrap();
+        let msg = msgs.first().unwrap();
+        assume_eq!(msg.tip, "CAS_RESP")?;
+        let data: CasRespMessage = serde_json::from_str(&msg.data).unwrap();
+        assume_eq!(data.key, key)?;
+        if data.success {
+            assume_eq!(data.value, Some(new.as_str()))?;
+            winners.push(new.clone());
+        }
+        observed.push(data.value.map(|v| v.to_string()));
+    }
+    assume!(
+        winners.len() == 1,
+        format!(
+            "Expected exactly one successful CAS for key {}, got {}",
+            key,
+            winners.len()
+        )
+    )?;
+    // failed operations are ordered after the winner, so they should observe its value
+    let winner = winners.pop().unwrap();
+    assume!(
+        observed.iter().all(|v| v.as_ref() == Some(&winner)),
+        format!("Failed CAS for key {} returned not the winning value", key)
+    )?;
+    Ok(winner)
+}
+
+fn check_multi_get(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    keys: &[String],
+    expected: &BTreeMap<String, Option<String>>,
+    max_steps: u32,
+) -> TestResult {
+    sys.send_local(
+        JsonMessage::from("MULTI_GET", &MultiGetMessage { keys }),
+        node,
+    );
+    let res = sys.step_until_local_message_max_steps(node, max_steps);
+    assume!(
+        res.is_ok(),
+        format!("MULTI_GET_RESP is not returned by {}", node)
+    )?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "MULTI_GET_RESP")?;
+    let data: MultiGetRespMessage = serde_json::from_str(&msg.data).unwrap();
+    assume!(
+        data.records.len() == keys.len(),
+        format!(
+            "MULTI_GET result is not complete: expected {} keys, got {}",
+            keys.len(),
+            data.records.len()
+        )
+    )?;
+    assume_eq!(data.records, *expected)?;
+    Ok(true)
+}
+
+fn check_multi_put(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    records: &BTreeMap<String, String>,
+    max_steps: u32,
+) -> TestResult {
+    sys.send_local(
+        JsonMessage::from("MULTI_PUT", &MultiPutMessage { records }),
+        node,
+    );
+    let res = sys.step_until_local_message_max_steps(node, max_steps);
+    assume!(
+        res.is_ok(),
+        format!("MULTI_PUT_RESP is not returned by {}", node)
+    )?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "MULTI_PUT_RESP")?;
+    let data: MultiPutRespMessage = serde_json::from_str(&msg.data).unwrap();
+    assume_eq!(data.records, *records)?;
+    Ok(true)
+}
+
+fn check_scan(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    prefix: &str,
+    limit: usize,
+    expected: &[(String, String)],
+    max_steps: u32,
+) -> TestResult {
+    sys.send_local(
+        JsonMessage::from("SCAN", &ScanMessage { prefix, limit }),
+        node,
+    );
+    let res = sys.step_until_local_message_max_steps(node, max_steps);
+    assume!(
+        res.is_ok(),
+        format!("SCAN_RESP is not returned by {}", node)
+    )?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "SCAN_RESP")?;
+    let data: ScanRespMessage = serde_json::from_str(&msg.data).unwrap();
+    assume_eq!(data.prefix, prefix)?;
+    let records: Vec<(String, String)> =
+        data.records.into_iter().map(|r| (r.key, r.value)).collect();
+    assume!(
+        records == *expected,
+        format!(
+            "Wrong SCAN result for prefix {} and limit {}: expected {} records, got {}",
+            prefix,
+            limit,
+            expected.len(),
+            records.len()
+        )
+    )
+}
+
+// Records with keys starting with prefix sorted by key
+fn expected_scan(
+    kv: &HashMap<String, String>,
+    prefix: &str,
+    limit: usize,
+) -> Vec<(String, String)> {
+    let mut records: Vec<(String, String)> = kv
+        .iter()
+        .filter(|(k, _)| k.starts_with(prefix))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    records.sort();
+    records.truncate(limit);
+    records
+}
+
+// Scans with prefixes of different length (including one matching no keys) and limits
+fn check_scans(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+    kv: &HashMap<String, String>,
+    rand: &mut Pcg64,
+) -> TestResult {
+    let mut keys: Vec<&String> = kv.keys().collect();
+    keys.sort();
+    let mut prefixes = vec!["".to_string(), "not-existing".to_string()];
+    for len in 1..=2 {
+        for key in keys.choose_multiple(rand, 3) {
+            prefixes.push(key[..len].to_string());
+        }
+    }
+    for prefix in prefixes.iter() {
+        for limit in [1, 10, kv.len()] {
+            let node = nodes.choose(rand).unwrap();
+            let expected = expected_scan(kv, prefix, limit);
+            check_scan(sys, node, prefix, limit, &expected, 1000)?;
+        }
+    }
+    Ok(true)
+}
+
 fn dump_keys(sys: &mut System<JsonMessage>, node: &str) -> Result<HashSet<String>, String> {
     sys.send_local(JsonMessage::from("DUMP_KEYS", &DumpKeysMessage {}), node);
     let res = sys.step_until_local_message_max_steps(node, 100);
@@ -210,6 +608,30 @@ fn key_distribution(
     Ok(dist)
 }
 
+// Requests keys from all nodes at once to get a consistent snapshot at the current time
+fn snapshot_keys(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+) -> Result<HashMap<String, HashSet<String>>, String> {
+    for node in nodes.iter() {
+        sys.send_local(JsonMessage::from("DUMP_KEYS", &DumpKeysMessage {}), node);
+    }
+    let mut dist = HashMap::new();
+    for node in nodes.iter() {
+        let res = sys.step_until_local_message_max_steps(node, 100);
+        assume!(
+            res.is_ok(),
+            format!("DUMP_KEYS_RESP is not returned by {}", node)
+        )?;
+        let msgs = res.unwrap();
+        let msg = msgs.first().unwrap();
+        assume_eq!(msg.tip, "DUMP_KEYS_RESP")?;
+        let data: DumpKeysRespMessage = serde_json::from_str(&msg.data).unwrap();
+        dist.insert(node.clone(), data.keys);
+    }
+    Ok(dist)
+}
+
 fn count_records(sys: &mut System<JsonMessage>, node: &str) -> Result<u64, String> {
     sys.send_local(
         JsonMessage::from("COUNT_RECORDS", &CountRecordsMessage {}),
@@ -228,9 +650,14 @@ fn count_records(sys: &mut System<JsonMessage>, node: &str) -> Result<u64, Strin
 }
 
 fn send_node_added(sys: &mut System<JsonMessage>, added: &str) {
+    send_node_added_with_weight(sys, added, None);
+}
+
+// Also used to change the weight of already added node
+fn send_node_added_with_weight(sys: &mut System<JsonMessage>, added: &str, weight: Option<f64>) {
     for node in sys.get_node_ids() {
         sys.send_local(
-            JsonMessage::from("NODE_ADDED", &NodeAddedMessage { id: added }),
+            JsonMessage::from("NODE_ADDED", &NodeAddedMessage { id: added, weight }),
             &node,
         );
     }
@@ -251,6 +678,25 @@ fn step_until_stabilized(
     expected_keys: u64,
     steps_per_iter: u32,
     max_steps: u32,
+) -> TestResult {
+    step_until_stabilized_in_range(
+        sys,
+        nodes,
+        expected_keys,
+        expected_keys,
+        steps_per_iter,
+        max_steps,
+    )
+}
+
+// Used when the exact number of keys is not known (e.g. some keys can be lost)
+fn step_until_stabilized_in_range(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+    min_keys: u64,
+    max_keys: u64,
+    steps_per_iter: u32,
+    max_steps: u32,
 ) -> TestResult {
     let mut stabilized = false;
     let mut steps = 0;
@@ -275,16 +721,21 @@ fn step_until_stabilized(
             counts.insert(node, count);
             total_count += count;
         }
-        if total_count == expected_keys && !count_changed {
+        if total_count >= min_keys && total_count <= max_keys && !count_changed {
             stabilized = true;
         }
     }
 
+    let expected = if min_keys == max_keys {
+        format!("{}", min_keys)
+    } else {
+        format!("{}..{}", min_keys, max_keys)
+    };
     assume!(
         stabilized,
         format!(
             "Keys distribution is not stabilized (keys observed = {}, expected = {})",
-            total_count, expected_keys
+            total_count, expected
         )
     )
 }
@@ -295,6 +746,25 @@ fn check(
     expected: &HashMap<String, String>,
     check_values: bool,
     check_distribution: bool,
+) -> TestResult {
+    check_with_weights(
+        sys,
+        nodes,
+        expected,
+        check_values,
+        check_distribution,
+        &HashMap::new(),
+    )
+}
+
+// Nodes missing in weights have weight 1
+fn check_with_weights(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+    expected: &HashMap<String, String>,
+    check_values: bool,
+    check_distribution: bool,
+    weights: &HashMap<String, f64>,
 ) -> TestResult {
     let mut stored_keys = HashSet::new();
     let mut node_key_counts = Vec::new();
@@ -331,12 +801,21 @@ fn check(
 
     // check keys distribution
     if check_distribution {
-        let target_count = (expected.len() as f64 / node_key_counts.len() as f64).round();
+        let node_weights: Vec<f64> = nodes
+            .iter()
+            .map(|node| *weights.get(node).unwrap_or(&1.))
+            .collect();
+        let total_weight: f64 = node_weights.iter().sum();
+        let target_counts: Vec<f64> = node_weights
+            .iter()
+            .map(|w| (expected.len() as f64 * w / total_weight).round())
+            .collect();
         let max_count = *node_key_counts.iter().max().unwrap();
         let min_count = *node_key_counts.iter().min().unwrap();
         let deviations: Vec<f64> = node_key_counts
             .iter()
-            .map(|x| (target_count - *x as f64).abs() / target_count)
+            .zip(target_counts.iter())
+            .map(|(x, target_count)| (target_count - *x as f64).abs() / target_count)
             .collect();
         let avg_deviation = deviations.iter().sum::<f64>() / node_key_counts.len() as f64;
         let max_deviation = deviations
@@ -345,9 +824,18 @@ fn check(
             .max()
             .unwrap();
         println!("\nStored keys per node:");
-        println!("  - target: {}", target_count);
-        println!("  - min: {}", min_count);
-        println!("  - max: {}", max_count);
+        if node_weights.iter().all(|w| *w == node_weights[0]) {
+            println!("  - target: {}", target_counts[0]);
+            println!("  - min: {}", min_count);
+            println!("  - max: {}", max_count);
+        } else {
+            for (i, node) in nodes.iter().enumerate() {
+                println!(
+                    "  - node {} (weight {}): {}, target {}",
+                    node, node_weights[i], node_key_counts[i], target_counts[i]
+                );
+            }
+        }
         println!("  - average deviation from target: {:.3}", avg_deviation);
         println!("  - max deviation from target: {:.3}", max_deviation);
         assume!(
@@ -387,33 +875,835 @@ fn check_moved_keys(
     )
 }
 
-const SYMBOLS: [char; 36] = [
-    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
-    't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
-];
-const WEIGHTS: [usize; 36] = [
-    13, 16, 3, 8, 8, 5, 6, 23, 4, 8, 24, 12, 2, 1, 1, 10, 5, 8, 10, 1, 24, 3, 1, 8, 12, 22, 5, 20,
-    18, 5, 5, 2, 1, 3, 16, 22,
-];
+#[derive(Clone, Debug)]
+enum OpKind {
+    Get,
+    Put(String),
+    Delete,
+}
 
-fn random_string(length: usize, rand: &mut Pcg64) -> String {
-    let dist = WeightedIndex::new(&WEIGHTS).unwrap();
-    rand.sample_iter(&dist)
-        .take(length)
-        .map(|x| SYMBOLS[x])
-        .collect()
+#[derive(Clone, Debug)]
+struct Operation {
+    node: String,
+    key: String,
+    kind: OpKind,
+    // value returned by GET or DELETE
+    result: Option<String>,
+    invoked: f64,
+    completed: f64,
 }
 
-// TESTS ---------------------------------------------------------------------------------------------------------------
+const LINEARIZABILITY_KEYS: usize = 20;
+const LINEARIZABILITY_DURATION: f64 = 10.;
+const LINEARIZABILITY_TIMEOUT: f64 = 10.;
+const LINEARIZABILITY_MAX_IDLE_STEPS: u32 = 10000;
 
-fn test_single_node(config: &TestConfig) -> TestResult {
-    let mut sys = build_system(config, false);
-    let mut rand = Pcg64::seed_from_u64(config.seed);
+fn invoke_operation(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    key: &str,
+    kind: OpKind,
+) -> Operation {
+    let msg = match &kind {
+        OpKind::Get => JsonMessage::from("GET", &GetMessage { key }),
+        OpKind::Put(value) => JsonMessage::from(
+            "PUT",
+            &PutMessage {
+                key,
+                value,
+                ttl: None,
+            },
+        ),
+        OpKind::Delete => JsonMessage::from("DELETE", &DeleteMessage { key }),
+    };
+    sys.send_local(msg, node);
+    Operation {
+        node: node.to_string(),
+        key: key.to_string(),
+        kind,
+        result: None,
+        invoked: sys.time(),
+        completed: f64::INFINITY,
+    }
+}
 
-    let node = "0";
-    let key = random_string(8, &mut rand).to_uppercase();
-    let value = random_string(8, &mut rand);
-    let max_steps = 10;
+fn complete_operation(op: &mut Operation, msg: &JsonMessage, time: f64) -> TestResult {
+    op.completed = time;
+    match &op.kind {
+        OpKind::Get => {
+            assume_eq!(msg.tip, "GET_RESP")?;
+            let data: GetRespMessage = serde_json::from_str(&msg.data).unwrap();
+            assume_eq!(data.key, op.key)?;
+            op.result = data.value.map(|v| v.to_string());
+        }
+        OpKind::Put(value) => {
+            assume_eq!(msg.tip, "PUT_RESP")?;
+            let data: PutRespMessage = serde_json::from_str(&msg.data).unwrap();
+            assume_eq!(data.key, op.key)?;
+            assume_eq!(data.value, value)?;
+        }
+        OpKind::Delete => {
+            assume_eq!(msg.tip, "DELETE_RESP")?;
+            let data: DeleteRespMessage = serde_json::from_str(&msg.data).unwrap();
+            assume_eq!(data.key, op.key)?;
+            op.result = data.value.map(|v| v.to_string());
+        }
+    }
+    Ok(true)
+}
+
+fn random_operation_kind(rand: &mut Pcg64) -> OpKind {
+    let p: f64 = rand.gen();
+    if p < 0.4 {
+        OpKind::Get
+    } else if p < 0.8 {
+        OpKind::Put(random_string(8, rand))
+    } else {
+        OpKind::Delete
+    }
+}
+
+// Each client node runs random operations one by one, so that operations from different nodes overlap.
+// Mailboxes of clients are checked after each step, so response time is the time of the response.
+fn run_concurrent_workload(
+    sys: &mut System<JsonMessage>,
+    clients: &[String],
+    keys: &[String],
+    duration: f64,
+    rand: &mut Pcg64,
+) -> Result<Vec<Operation>, String> {
+    let end_time = sys.time() + duration;
+    let mut history = Vec::new();
+    let mut pending: HashMap<String, Operation> = HashMap::new();
+    let mut idle_steps = 0;
+    loop {
+        if sys.time() < end_time {
+            for client in clients.iter() {
+                if !pending.contains_key(client) {
+                    let key = keys.choose(rand).unwrap();
+                    let kind = random_operation_kind(rand);
+                    pending.insert(client.clone(), invoke_operation(sys, client, key, kind));
+                }
+            }
+        }
+        if pending.is_empty() {
+            break;
+        }
+        let time_before = sys.time();
+        let stepped = sys.step();
+        let mut completed = false;
+        for client in clients.iter() {
+            if pending.contains_key(client) {
+                if let Some(msgs) = sys.check_mailbox(client) {
+                    let mut op = pending.remove(client).unwrap();
+                    complete_operation(&mut op, msgs.first().unwrap(), sys.time())?;
+                    history.push(op);
+                    completed = true;
+                }
+            }
+        }
+        // no events left, so pending operations will never complete
+        let stuck = !stepped && !completed && !pending.is_empty();
+        if stuck || sys.time() > end_time + LINEARIZABILITY_TIMEOUT {
+            let mut clients: Vec<&String> = pending.keys().collect();
+            clients.sort();
+            return Err(format!(
+                "Responses are not returned by nodes: {:?}",
+                clients
+            ));
+        }
+        // operations completed by nodes locally do not advance the time, so the workload could never end
+        if sys.time() == time_before {
+            idle_steps += 1;
+        } else {
+            idle_steps = 0;
+        }
+        assume!(
+            idle_steps <= LINEARIZABILITY_MAX_IDLE_STEPS,
+            format!(
+                "Simulation time does not advance for {} steps",
+                LINEARIZABILITY_MAX_IDLE_STEPS
+            )
+        )?;
+    }
+    Ok(history)
+}
+
+fn perform_operation(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    key: &str,
+    kind: OpKind,
+) -> Result<Operation, String> {
+    let mut op = invoke_operation(sys, node, key, kind);
+    let res = sys.step_until_local_message_max_steps(node, 100);
+    assume!(res.is_ok(), format!("Response is not returned by {}", node))?;
+    let msgs = res.unwrap();
+    complete_operation(&mut op, msgs.first().unwrap(), sys.time())?;
+    Ok(op)
+}
+
+fn apply_operation(op: &Operation, state: &Option<String>) -> Option<Option<String>> {
+    match &op.kind {
+        OpKind::Get if op.result == *state => Some(state.clone()),
+        OpKind::Put(value) => Some(Some(value.clone())),
+        OpKind::Delete if op.result == *state => Some(None),
+        _ => None,
+    }
+}
+
+// Searches for a valid linearization of single key operations (Wing & Gong algorithm with memoization)
+fn linearize(
+    ops: &[&Operation],
+    done: &mut Vec<bool>,
+    state: &Option<String>,
+    visited: &mut HashSet<(Vec<bool>, Option<String>)>,
+) -> bool {
+    // the next operation must be invoked before all remaining operations are completed
+    let min_completed = ops
+        .iter()
+        .zip(done.iter())
+        .filter(|(_, done)| !**done)
+        .map(|(op, _)| op.completed)
+        .fold(f64::INFINITY, f64::min);
+    if min_completed == f64::INFINITY {
+        return true;
+    }
+    for i in 0..ops.len() {
+        if done[i] || ops[i].invoked > min_completed {
+            continue;
+        }
+        if let Some(next_state) = apply_operation(ops[i], state) {
+            done[i] = true;
+            if visited.insert((done.clone(), next_state.clone()))
+                && linearize(ops, done, &next_state, visited)
+            {
+                return true;
+            }
+            done[i] = false;
+        }
+    }
+    false
+}
+
+fn print_history(ops: &[&Operation], initial: Option<&String>) {
+    println!("Initial value: {:?}", initial);
+    for op in ops.iter() {
+        let op_str = match &op.kind {
+            OpKind::Get => format!("GET -> {:?}", op.result),
+            OpKind::Put(value) => format!("PUT {:?}", value),
+            OpKind::Delete => format!("DELETE -> {:?}", op.result),
+        };
+        println!(
+            "  [{:>8.3} - {:>8.3}] node {}: {}",
+            op.invoked, op.completed, op.node, op_str
+        );
+    }
+}
+
+// Checks that history is linearizable given the initial values of keys,
+// since linearizability is local the history of each key is checked separately
+fn check_linearizability(history: &[Operation], initial: &HashMap<String, String>) -> TestResult {
+    let mut keys: Vec<&String> = history.iter().map(|op| &op.key).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let mut ops: Vec<&Operation> = history.iter().filter(|op| op.key == *key).collect();
+        ops.sort_by(|a, b| a.invoked.partial_cmp(&b.invoked).unwrap());
+        let mut done = vec![false; ops.len()];
+        let mut visited = HashSet::new();
+        let state = initial.get(key).cloned();
+        if !linearize(&ops, &mut done, &state, &mut visited) {
+            println!("\nHistory of key {} is not linearizable:", key);
+            print_history(&ops, initial.get(key));
+            return Err(format!("History of key {} is not linearizable", key));
+        }
+    }
+    println!("\nHistory of {} operations is linearizable", history.len());
+    Ok(true)
+}
+
+// Runs concurrent operations from all nodes while the keys are rebalanced,
+// checks the history and waits for the rebalancing to complete
+fn check_concurrent_operations(
+    sys: &mut System<JsonMessage>,
+    nodes: &Vec<String>,
+    kv: &mut HashMap<String, String>,
+    rand: &mut Pcg64,
+) -> TestResult {
+    // mix of existing and new keys
+    let mut keys: Vec<String> = kv.keys().cloned().collect();
+    keys.sort();
+    let mut keys: Vec<String> = keys
+        .choose_multiple(rand, LINEARIZABILITY_KEYS / 2)
+        .cloned()
+        .collect();
+    for _ in 0..LINEARIZABILITY_KEYS / 2 {
+        keys.push(random_string(8, rand).to_uppercase());
+    }
+
+    let mut history = run_concurrent_workload(sys, nodes, &keys, LINEARIZABILITY_DURATION, rand)?;
+    // read final values
+    for key in keys.iter() {
+        let node = nodes.choose(rand).unwrap();
+        history.push(perform_operation(sys, node, key, OpKind::Get)?);
+    }
+    check_linearizability(&history, kv)?;
+
+    for op in history.iter().skip(history.len() - keys.len()) {
+        match &op.result {
+            Some(value) => kv.insert(op.key.clone(), value.clone()),
+            None => kv.remove(&op.key),
+        };
+    }
+    step_until_stabilized(sys, nodes, kv.len() as u64, 100, 1000)?;
+    check(sys, nodes, kv, false, false)
+}
+
+const MIGRATION_MAX_TRAFFIC_RATIO: f64 = 5.;
+
+// Size of records encoded as JSON object, which is the minimal size of message data needed to transfer them
+fn records_json_size<'a>(
+    kv: &HashMap<String, String>,
+    keys: impl Iterator<Item = &'a String>,
+) -> u64 {
+    let records: BTreeMap<&String, &String> = keys.map(|k| (k, kv.get(k).unwrap())).collect();
+    serde_json::to_string(&records).unwrap().len() as u64
+}
+
+// Compares network traffic and messages used for rebalancing with the minimum needed
+// to move the reassigned records: the moved records of each pair of old and new owner are sent as a single
+// JSON object, and each old owner and each new owner takes part in at least one message
+fn check_migration_traffic(
+    kv: &HashMap<String, String>,
+    before: &HashMap<String, HashSet<String>>,
+    after: &HashMap<String, HashSet<String>>,
+    traffic: u64,
+    message_count: u64,
+) -> TestResult {
+    let mut owners_before = HashMap::new();
+    for (node, keys) in before.iter() {
+        for key in keys.iter() {
+            owners_before.insert(key, node);
+        }
+    }
+    let mut transfers: HashMap<(&String, &String), Vec<&String>> = HashMap::new();
+    for (node, keys) in after.iter() {
+        for key in keys.iter() {
+            if let Some(owner) = owners_before.get(key) {
+                if *owner != node {
+                    transfers.entry((*owner, node)).or_default().push(key);
+                }
+            }
+        }
+    }
+    let min_traffic: u64 = transfers
+        .values()
+        .map(|keys| records_json_size(kv, keys.iter().copied()))
+        .sum();
+    let sources: HashSet<&String> = transfers.keys().map(|(from, _)| *from).collect();
+    let destinations: HashSet<&String> = transfers.keys().map(|(_, to)| *to).collect();
+    let min_message_count = sources.len().max(destinations.len()) as u64;
+    let dataset_traffic = records_json_size(kv, kv.keys());
+    let traffic_ratio = traffic as f64 / min_traffic.max(1) as f64;
+    println!("\nRebalancing cost:");
+    println!(
+        "  - traffic: {} (min {}, ratio {:.2})",
+        traffic, min_traffic, traffic_ratio
+    );
+    println!(
+        "  - messages: {} (min {})",
+        message_count, min_message_count
+    );
+    assume!(
+        message_count >= min_message_count,
+        format!(
+            "Rebalancing used {} messages, while records are moved from {} nodes to {} nodes",
+            message_count,
+            sources.len(),
+            destinations.len()
+        )
+    )?;
+    assume!(
+        traffic < dataset_traffic,
+        format!(
+            "Rebalancing traffic {} is not less than the whole dataset size {}",
+            traffic, dataset_traffic
+        )
+    )?;
+    assume!(
+        traffic_ratio <= MIGRATION_MAX_TRAFFIC_RATIO,
+        format!(
+            "Rebalancing traffic is more than {} times above the minimum",
+            MIGRATION_MAX_TRAFFIC_RATIO
+        )
+    )
+}
+
+// Minimal number of keys to move when node weights change (nodes missing in weights are not in the system),
+// equals to the total increase of target key counts
+fn optimal_moved_keys(
+    keys_count: usize,
+    weights_before: &HashMap<String, f64>,
+    weights_after: &HashMap<String, f64>,
+) -> u64 {
+    let total_before: f64 = weights_before.values().sum();
+    let total_after: f64 = weights_after.values().sum();
+    let mut moved = 0.;
+    for (node, weight) in weights_after.iter() {
+        let share_before = weights_before.get(node).unwrap_or(&0.) / total_before;
+        let share_after = weight / total_after;
+        if share_after > share_before {
+            moved += keys_count as f64 * (share_after - share_before);
+        }
+    }
+    moved.round() as u64
+}
+
+#[derive(Copy, Clone)]
+enum KeyPopularity {
+    Uniform,
+    // probability of choosing the key with rank r is proportional to 1 / r^s
+    Zipf(f64),
+    // given fraction of requests goes to a single key, the rest are uniform
+    HotKey(f64),
+}
+
+// Chooses keys for requests according to the popularity distribution,
+// key ranks are assigned in the order of given keys
+struct KeyChooser {
+    keys: Vec<String>,
+    popularity: KeyPopularity,
+    zipf_dist: Option<WeightedIndex<f64>>,
+}
+
+impl KeyChooser {
+    fn new(keys: Vec<String>, popularity: KeyPopularity) -> Self {
+        let zipf_dist = match popularity {
+            KeyPopularity::Zipf(s) => {
+                let weights: Vec<f64> = (1..=keys.len())
+                    .map(|rank| 1. / (rank as f64).powf(s))
+                    .collect();
+                Some(WeightedIndex::new(&weights).unwrap())
+            }
+            _ => None,
+        };
+        Self {
+            keys,
+            popularity,
+            zipf_dist,
+        }
+    }
+
+    fn choose(&self, rand: &mut Pcg64) -> &String {
+        match self.popularity {
+            KeyPopularity::Uniform => self.keys.choose(rand).unwrap(),
+            KeyPopularity::Zipf(_) => &self.keys[self.zipf_dist.as_ref().unwrap().sample(rand)],
+            KeyPopularity::HotKey(fraction) => {
+                if rand.gen_bool(fraction) {
+                    &self.keys[0]
+                } else {
+                    self.keys.choose(rand).unwrap()
+                }
+            }
+        }
+    }
+}
+
+fn request_load(sys: &System<JsonMessage>, nodes: &[String]) -> HashMap<String, u64> {
+    let mut load = HashMap::new();
+    for node in nodes.iter() {
+        load.insert(
+            node.clone(),
+            sys.get_sent_message_count(node) + sys.get_received_message_count(node),
+        );
+    }
+    load
+}
+
+// Prints per-node load (messages sent and received between two measurements) and returns max/avg load ratio
+fn report_load_imbalance(
+    nodes: &[String],
+    before: &HashMap<String, u64>,
+    after: &HashMap<String, u64>,
+) -> f64 {
+    let loads: Vec<u64> = nodes
+        .iter()
+        .map(|node| after.get(node).unwrap() - before.get(node).unwrap())
+        .collect();
+    let total: u64 = loads.iter().sum();
+    let avg_load = total as f64 / loads.len() as f64;
+    let max_load = *loads.iter().max().unwrap();
+    let min_load = *loads.iter().min().unwrap();
+    let imbalance = max_load as f64 / avg_load;
+    println!("\nRequest load per node (messages):");
+    println!("  - total: {}", total);
+    println!("  - min: {}", min_load);
+    println!("  - max: {}", max_load);
+    println!("  - avg: {:.1}", avg_load);
+    println!("  - imbalance (max/avg): {:.3}", imbalance);
+    imbalance
+}
+
+const LOAD_KEYS: usize = 1000;
+const LOAD_REQUESTS: usize = 5000;
+const LOAD_MAX_IMBALANCE: f64 = 1.5;
+
+// Runs GET and PUT requests with given key popularity from random nodes and returns load imbalance
+fn run_skewed_workload(config: &TestConfig, popularity: KeyPopularity) -> Result<f64, String> {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+
+    // insert random key-value pairs
+    let mut kv = HashMap::new();
+    let mut keys = Vec::new();
+    for _ in 0..LOAD_KEYS {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = nodes.choose(&mut rand).unwrap();
+        check_put(&mut sys, node, &k, &v, 100)?;
+        kv.insert(k.clone(), v);
+        keys.push(k);
+    }
+
+    // run requests (90% reads, 10% writes) and measure load
+    let chooser = KeyChooser::new(keys, popularity);
+    let load_before = request_load(&sys, &nodes);
+    for _ in 0..LOAD_REQUESTS {
+        let key = chooser.choose(&mut rand);
+        let node = nodes.choose(&mut rand).unwrap();
+        if rand.gen_bool(0.9) {
+            check_get(&mut sys, node, key, kv.get(key).map(|v| v.as_str()), 100)?;
+        } else {
+            let value = random_string(8, &mut rand);
+            check_put(&mut sys, node, key, &value, 100)?;
+            kv.insert(key.clone(), value);
+        }
+    }
+    let load_after = request_load(&sys, &nodes);
+    Ok(report_load_imbalance(&nodes, &load_before, &load_after))
+}
+
+#[derive(Copy, Clone, Debug)]
+enum PlacementScheme {
+    ConsistentHashing(u32),
+    Rendezvous,
+}
+
+fn get_placement_scheme(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+) -> Result<PlacementScheme, String> {
+    sys.send_local(JsonMessage::from("PLACEMENT", &PlacementMessage {}), node);
+    let res = sys.step_until_local_message_max_steps(node, 100);
+    assume!(
+        res.is_ok(),
+        format!("PLACEMENT_RESP is not returned by {}", node)
+    )?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "PLACEMENT_RESP")?;
+    let data: PlacementRespMessage = serde_json::from_str(&msg.data).unwrap();
+    match (data.scheme.as_str(), data.vnodes) {
+        ("consistent_hashing", Some(vnodes)) if vnodes > 0 => {
+            Ok(PlacementScheme::ConsistentHashing(vnodes))
+        }
+        ("rendezvous", _) => Ok(PlacementScheme::Rendezvous),
+        _ => Err(format!("Unsupported placement scheme: {}", data.scheme)),
+    }
+}
+
+// First 8 bytes of MD5 as little-endian integer
+fn placement_hash(s: &str) -> u64 {
+    let hash = md5::compute(s);
+    LittleEndian::read_u64(&hash.0[..8])
+}
+
+// Node ids are compared as numbers, so that ties are broken in favor of the node with the smaller id
+fn node_id_order(id: &str) -> (u64, &str) {
+    (id.parse().unwrap_or(u64::MAX), id)
+}
+
+// Computes key owners for the declared placement scheme independently of the solution
+struct PlacementOracle {
+    scheme: PlacementScheme,
+    nodes: Vec<String>,
+    // sorted (point, node) pairs for consistent hashing
+    ring: Vec<(u64, String)>,
+}
+
+impl PlacementOracle {
+    fn new(scheme: PlacementScheme, nodes: &[String]) -> Self {
+        let mut ring = Vec::new();
+        if let PlacementScheme::ConsistentHashing(vnodes) = scheme {
+            for node in nodes.iter() {
+                for i in 0..vnodes {
+                    ring.push((placement_hash(&format!("{}#{}", node, i)), node.clone()));
+                }
+            }
+            ring.sort_by(|(p1, n1), (p2, n2)| {
+                (p1, node_id_order(n1)).cmp(&(p2, node_id_order(n2)))
+            });
+        }
+        let mut nodes = nodes.to_vec();
+        nodes.sort_by(|a, b| node_id_order(a).cmp(&node_id_order(b)));
+        Self {
+            scheme,
+            nodes,
+            ring,
+        }
+    }
+
+    fn owner(&self, key: &str) -> &String {
+        match self.scheme {
+            PlacementScheme::ConsistentHashing(_) => {
+                let point = placement_hash(key);
+                // ties are resolved by node id due to the ring order
+                let idx = self.ring.partition_point(|(p, _)| *p < point);
+                &self.ring[idx % self.ring.len()].1
+            }
+            PlacementScheme::Rendezvous => {
+                let mut best = &self.nodes[0];
+                let mut best_hash = placement_hash(&format!("{}#{}", best, key));
+                for node in self.nodes.iter().skip(1) {
+                    let hash = placement_hash(&format!("{}#{}", node, key));
+                    if hash > best_hash {
+                        best = node;
+                        best_hash = hash;
+                    }
+                }
+                best
+            }
+        }
+    }
+}
+
+// Compares the actual keys placement with the expected one for the declared scheme
+fn check_placement(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+    scheme: PlacementScheme,
+) -> TestResult {
+    let oracle = PlacementOracle::new(scheme, nodes);
+    let mut misplaced = Vec::new();
+    let mut total = 0;
+    for node in nodes.iter() {
+        for key in dump_keys(sys, node)? {
+            let expected = oracle.owner(&key);
+            if expected != node {
+                misplaced.push((key, node.clone(), expected.clone()));
+            }
+            total += 1;
+        }
+    }
+    println!("\nPlacement for {:?}:", scheme);
+    println!("  - misplaced keys: {} of {}", misplaced.len(), total);
+    misplaced.sort();
+    for (key, node, expected) in misplaced.iter().take(10) {
+        println!(
+            "  - key {} is stored on {}, expected on {}",
+            key, node, expected
+        );
+    }
+    assume!(
+        misplaced.is_empty(),
+        format!("{} keys are misplaced", misplaced.len())
+    )
+}
+
+// Request is sent directly to the key owner which replies back
+const ROUTING_MAX_HOPS: u64 = 2;
+const ROUTING_KEYS: usize = 100;
+const ROUTING_SCALES: [u32; 3] = [1, 2, 4];
+
+// Hops are measured as the number of network messages caused by a single client operation
+fn count_hops<F>(sys: &mut System<JsonMessage>, op: F) -> Result<u64, String>
+where
+    F: FnOnce(&mut System<JsonMessage>) -> TestResult,
+{
+    let messages_before = sys.get_network_message_count();
+    op(sys)?;
+    Ok(sys.get_network_message_count() - messages_before)
+}
+
+thread_local! {
+    // key of the operation in progress and the number of received messages mentioning it
+    static TRACED_KEY: RefCell<Option<(String, u64)>> = const { RefCell::new(None) };
+}
+
+// Clears the key left traced by a failed operation of the previous test
+fn reset_hop_tracing() {
+    TRACED_KEY.with(|traced| *traced.borrow_mut() = None);
+}
+
+// Wraps solution node to count received messages mentioning the traced key,
+// which allows to measure hops of an operation while other messages (e.g. for rebalancing) are in flight
+struct HopTracer {
+    node: PyNode,
+}
+
+impl HopTracer {
+    fn new(node: PyNode) -> Self {
+        Self { node }
+    }
+}
+
+impl Node<JsonMessage> for HopTracer {
+    fn id(&self) -> &String {
+        self.node.id()
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        TRACED_KEY.with(|traced| {
+            if let Some((key, count)) = traced.borrow_mut().as_mut() {
+                // keys are compared as JSON strings to avoid matching keys which are substrings of other keys
+                if msg.data.contains(&serde_json::to_string(key).unwrap()) {
+                    *count += 1;
+                }
+            }
+        });
+        self.node.on_message(msg, from, ctx);
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        self.node.on_local_message(msg, ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        self.node.on_timer(timer, ctx);
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.node.max_size()
+    }
+}
+
+// Hops are measured as the number of network messages mentioning the key of a single client operation
+// (requires nodes wrapped with HopTracer)
+fn count_key_hops<F>(sys: &mut System<JsonMessage>, key: &str, op: F) -> Result<u64, String>
+where
+    F: FnOnce(&mut System<JsonMessage>) -> TestResult,
+{
+    TRACED_KEY.with(|traced| *traced.borrow_mut() = Some((key.to_string(), 0)));
+    let res = op(sys);
+    let hops = TRACED_KEY.with(|traced| traced.borrow_mut().take().unwrap().1);
+    res?;
+    Ok(hops)
+}
+
+// Inserts random key-value pairs from random nodes and returns hops for each PUT
+fn put_counting_hops(
+    sys: &mut System<JsonMessage>,
+    kv: &mut HashMap<String, String>,
+    keys_count: usize,
+    rand: &mut Pcg64,
+) -> Result<Vec<u64>, String> {
+    let mut hops = Vec::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, rand).to_uppercase();
+        let v = random_string(8, rand);
+        let node = sys.get_node_ids().choose(rand).unwrap().clone();
+        hops.push(count_hops(sys, |sys| check_put(sys, &node, &k, &v, 100))?);
+        kv.insert(k, v);
+    }
+    Ok(hops)
+}
+
+// Reads each key from a random node and returns hops for each GET, reporting multi-hop forwarding chains
+fn get_counting_hops(
+    sys: &mut System<JsonMessage>,
+    kv: &HashMap<String, String>,
+    rand: &mut Pcg64,
+) -> Result<Vec<u64>, String> {
+    let mut keys: Vec<&String> = kv.keys().collect();
+    keys.sort();
+    let mut hops = Vec::new();
+    for k in keys {
+        let v = kv.get(k).unwrap();
+        let node = sys.get_node_ids().choose(rand).unwrap().clone();
+        let h = count_hops(sys, |sys| check_get(sys, &node, k, Some(v), 100))?;
+        if h > ROUTING_MAX_HOPS {
+            println!(
+                "Multi-hop routing: GET {} from node {} took {} hops",
+                k, node, h
+            );
+        }
+        hops.push(h);
+    }
+    Ok(hops)
+}
+
+// Reads all keys from random nodes during rebalancing and returns hops for each GET,
+// the returned values are not checked since the keys can be in transfer
+fn get_counting_key_hops(
+    sys: &mut System<JsonMessage>,
+    kv: &HashMap<String, String>,
+    rand: &mut Pcg64,
+) -> Result<Vec<u64>, String> {
+    let mut keys: Vec<&String> = kv.keys().collect();
+    keys.sort();
+    let mut hops = Vec::new();
+    for k in keys {
+        let node = sys.get_node_ids().choose(rand).unwrap().clone();
+        let h = count_key_hops(sys, k, |sys| {
+            sys.send_local(JsonMessage::from("GET", &GetMessage { key: k }), &node);
+            let res = sys.step_until_local_message_max_steps(&node, 100);
+            assume!(res.is_ok(), format!("GET_RESP is not returned by {}", node))
+        })?;
+        if h > ROUTING_MAX_HOPS {
+            println!(
+                "Multi-hop routing: GET {} from node {} took {} hops",
+                k, node, h
+            );
+        }
+        hops.push(h);
+    }
+    Ok(hops)
+}
+
+fn hop_stats(hops: &[u64]) -> (f64, u64) {
+    let avg = hops.iter().sum::<u64>() as f64 / hops.len() as f64;
+    (avg, *hops.iter().max().unwrap_or(&0))
+}
+
+fn check_max_hops(max_hops: u64) -> TestResult {
+    assume!(
+        max_hops <= ROUTING_MAX_HOPS,
+        format!(
+            "Request routing takes too many hops (max {}, expected at most {})",
+            max_hops, ROUTING_MAX_HOPS
+        )
+    )
+}
+
+const SYMBOLS: [char; 36] = [
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
+    't', 'u', 'v', 'w', 'x', 'y', 'z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+const WEIGHTS: [usize; 36] = [
+    13, 16, 3, 8, 8, 5, 6, 23, 4, 8, 24, 12, 2, 1, 1, 10, 5, 8, 10, 1, 24, 3, 1, 8, 12, 22, 5, 20,
+    18, 5, 5, 2, 1, 3, 16, 22,
+];
+
+fn random_string(length: usize, rand: &mut Pcg64) -> String {
+    let dist = WeightedIndex::new(&WEIGHTS).unwrap();
+    rand.sample_iter(&dist)
+        .take(length)
+        .map(|x| SYMBOLS[x])
+        .collect()
+}
+
+// TESTS ---------------------------------------------------------------------------------------------------------------
+
+fn test_single_node(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    let node = "0";
+    let key = random_string(8, &mut rand).to_uppercase();
+    let value = random_string(8, &mut rand);
+    let max_steps = 10;
 
     check_get(&mut sys, node, &key, None, max_steps)?;
     check_put(&mut sys, node, &key, &value, max_steps)?;
@@ -596,10 +1886,13 @@ fn test_node_removed_after_crash(config: &TestConfig) -> TestResult {
     check(&mut sys, &nodes, &kv, true, false)
 }
 
-fn test_migration(config: &TestConfig) -> TestResult {
+const REBALANCING_CRASH_STEPS: u32 = 20;
+
+// Crashes a node while keys are moved after the node addition. Only the keys that were not stored
+// on any of the remaining nodes at the time of crash can be lost, all other keys must be kept.
+fn test_crash_during_rebalancing(config: &TestConfig, crash_added: bool) -> TestResult {
     let mut sys = build_system(config, false);
     let mut rand = Pcg64::seed_from_u64(config.seed);
-    let mut nodes = sys.get_node_ids().clone();
 
     // insert random key-value pairs
     let keys_count = 1000;
@@ -612,17 +1905,222 @@ fn test_migration(config: &TestConfig) -> TestResult {
         kv.insert(k, v);
     }
 
-    // add new N nodes to the system
-    for i in 0..config.node_count {
-        let added = format!("{}", config.node_count + i);
-        add_node(&added, &mut sys, config);
-        send_node_added(&mut sys, &added);
-        nodes.push(added);
-        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
-    }
-
-    check(&mut sys, &nodes, &kv, false, false)?;
-
+    // add new node and crash the new node or one of the old nodes in the middle of rebalancing
+    let crashed = if crash_added {
+        format!("{}", sys.get_node_ids().len())
+    } else {
+        sys.get_node_ids().choose(&mut rand).unwrap().clone()
+    };
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+    sys.steps(REBALANCING_CRASH_STEPS);
+    let nodes = sys.get_node_ids();
+    let dist = snapshot_keys(&mut sys, &nodes)?;
+    sys.crash_node(&crashed);
+    send_node_removed(&mut sys, &crashed);
+
+    let mut kept_keys = HashSet::new();
+    for (node, keys) in dist.iter() {
+        if *node != crashed {
+            kept_keys.extend(keys.iter().cloned());
+        }
+    }
+    let lost_keys: HashSet<&String> = kv.keys().filter(|k| !kept_keys.contains(*k)).collect();
+    println!(
+        "\nKeys stored only on crashed node or in transit: {}",
+        lost_keys.len()
+    );
+
+    // run the system until key the distribution is stabilized
+    let nodes: Vec<String> = nodes.into_iter().filter(|x| *x != crashed).collect();
+    step_until_stabilized_in_range(
+        &mut sys,
+        &nodes,
+        kept_keys.len() as u64,
+        kv.len() as u64,
+        100,
+        1000,
+    )?;
+
+    // all kept keys are stored exactly once, lost keys are stored at most once
+    let mut stored_keys = HashSet::new();
+    let mut stored_count = 0;
+    for node in nodes.iter() {
+        let node_keys = dump_keys(&mut sys, node)?;
+        stored_count += node_keys.len();
+        stored_keys.extend(node_keys);
+    }
+    assume!(
+        kept_keys.iter().all(|k| stored_keys.contains(k)),
+        format!(
+            "Keys that were not lost are missing: {} of {}",
+            kept_keys.difference(&stored_keys).count(),
+            kept_keys.len()
+        )
+    )?;
+    assume!(
+        stored_keys.iter().all(|k| kv.contains_key(k)),
+        "Unexpected keys are stored"
+    )?;
+    assume!(
+        stored_count == stored_keys.len(),
+        "Keys are not stored on a single node"
+    )?;
+
+    // check values of some kept keys
+    let mut kept_keys: Vec<String> = kept_keys.into_iter().collect();
+    kept_keys.sort();
+    for k in kept_keys.choose_multiple(&mut rand, 100) {
+        let node = nodes.choose(&mut rand).unwrap();
+        check_get(&mut sys, node, k, kv.get(k).map(|v| v.as_str()), 100)?;
+    }
+    Ok(true)
+}
+
+fn test_source_crash_during_rebalancing(config: &TestConfig) -> TestResult {
+    test_crash_during_rebalancing(config, false)
+}
+
+fn test_destination_crash_during_rebalancing(config: &TestConfig) -> TestResult {
+    test_crash_during_rebalancing(config, true)
+}
+
+fn test_storage_node_restarted(config: &TestConfig) -> TestResult {
+    let _storage = StorageCleanup;
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+
+    // insert random key-value pairs and delete some of them
+    let keys_count = 100;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = nodes.choose(&mut rand).unwrap();
+        check_put(&mut sys, node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+    let mut keys: Vec<String> = kv.keys().cloned().collect();
+    keys.sort();
+    for k in keys.choose_multiple(&mut rand, keys_count / 10) {
+        let v = kv.remove(k).unwrap();
+        let node = nodes.choose(&mut rand).unwrap();
+        check_delete(&mut sys, node, k, Some(&v), 100)?;
+    }
+
+    // restart random nodes one by one
+    let restarted: Vec<String> = nodes
+        .choose_multiple(&mut rand, 3.min(nodes.len()))
+        .cloned()
+        .collect();
+    for node in restarted.iter() {
+        let count = count_records(&mut sys, node)?;
+        restart_nodes(std::slice::from_ref(node), &nodes, &mut sys, config);
+        assume_eq!(
+            count_records(&mut sys, node)?,
+            count,
+            format!("Node {} lost records after restart", node)
+        )?;
+    }
+
+    check(&mut sys, &nodes, &kv, true, false)
+}
+
+fn test_storage_cluster_restarted(config: &TestConfig) -> TestResult {
+    let _storage = StorageCleanup;
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+
+    // insert random key-value pairs and overwrite some of them
+    let keys_count = 100;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = nodes.choose(&mut rand).unwrap();
+        check_put(&mut sys, node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+    let mut keys: Vec<String> = kv.keys().cloned().collect();
+    keys.sort();
+    for k in keys.choose_multiple(&mut rand, keys_count / 10) {
+        let v = random_string(8, &mut rand);
+        let node = nodes.choose(&mut rand).unwrap();
+        check_put(&mut sys, node, k, &v, 100)?;
+        kv.insert(k.clone(), v);
+    }
+
+    // crash and restart all nodes at once
+    restart_nodes(&nodes, &nodes, &mut sys, config);
+
+    check(&mut sys, &nodes, &kv, true, false)
+}
+
+fn test_storage_restarted_after_rebalancing(config: &TestConfig) -> TestResult {
+    let _storage = StorageCleanup;
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids();
+
+    // insert random key-value pairs
+    let keys_count = 100;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = nodes.choose(&mut rand).unwrap();
+        check_put(&mut sys, node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new node and remove an old one, so that migrated records should be persisted
+    let added = format!("{}", nodes.len());
+    add_node_with_members(&added, &nodes, &mut sys, config);
+    send_node_added(&mut sys, &added);
+    nodes.push(added);
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+
+    let removed = nodes.remove(0);
+    send_node_removed(&mut sys, &removed);
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    sys.crash_node(&removed);
+
+    // crash and restart all remaining nodes at once
+    restart_nodes(&nodes, &nodes, &mut sys, config);
+
+    check(&mut sys, &nodes, &kv, true, false)
+}
+
+fn test_migration(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids().clone();
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new N nodes to the system
+    for i in 0..config.node_count {
+        let added = format!("{}", config.node_count + i);
+        add_node(&added, &mut sys, config);
+        send_node_added(&mut sys, &added);
+        nodes.push(added);
+        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    }
+
+    check(&mut sys, &nodes, &kv, false, false)?;
+
     // remove old N nodes
     for i in 0..config.node_count {
         let removed = format!("{}", i);
@@ -631,53 +2129,871 @@ fn test_migration(config: &TestConfig) -> TestResult {
         step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
     }
 
-    check(&mut sys, &nodes, &kv, false, false)
+    check(&mut sys, &nodes, &kv, false, false)
+}
+
+fn test_scale_up_down(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids().clone();
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new N nodes to the system
+    for i in 0..config.node_count {
+        let added = format!("{}", config.node_count + i);
+        add_node(&added, &mut sys, config);
+        send_node_added(&mut sys, &added);
+        nodes.push(added);
+        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    }
+
+    check(&mut sys, &nodes, &kv, false, false)?;
+
+    // remove new N nodes
+    for i in 0..config.node_count {
+        let removed = format!("{}", config.node_count + i);
+        send_node_removed(&mut sys, &removed);
+        nodes.remove(config.node_count as usize);
+        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    }
+
+    check(&mut sys, &nodes, &kv, false, false)
+}
+
+fn test_distribution(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 10000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    let nodes = sys.get_node_ids();
+    check(&mut sys, &nodes, &kv, false, true)
+}
+
+fn test_distribution_node_added(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut kv = HashMap::new();
+
+    // insert random key-value pairs
+    let keys_count = 10000;
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+    let dist_before = key_distribution(&mut sys)?;
+
+    // add new node to the system
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+
+    // run the system until key the distribution is stabilized
+    let nodes = sys.get_node_ids();
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    let dist_after = key_distribution(&mut sys)?;
+
+    let target_moved_keys = (keys_count as f64 / nodes.len() as f64).round() as u64;
+    check_moved_keys(&mut sys, &dist_before, &dist_after, target_moved_keys)?;
+
+    check(&mut sys, &nodes, &kv, false, true)
+}
+
+fn test_distribution_node_removed(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut kv = HashMap::new();
+
+    // insert random key-value pairs
+    let keys_count = 10000;
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+    let dist_before = key_distribution(&mut sys)?;
+
+    // remove a node from the system
+    let removed = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+    let count = count_records(&mut sys, &removed)?;
+    assume!(count > 0, "Node stores no records, bad distribution")?;
+    send_node_removed(&mut sys, &removed);
+
+    // run the system until key the distribution is stabilized
+    let nodes: Vec<String> = sys
+        .get_node_ids()
+        .into_iter()
+        .filter(|x| *x != removed)
+        .collect();
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    let dist_after = key_distribution(&mut sys)?;
+
+    let target_moved_keys = (keys_count as f64 / (nodes.len() + 1) as f64).round() as u64;
+    check_moved_keys(&mut sys, &dist_before, &dist_after, target_moved_keys)?;
+
+    check(&mut sys, &nodes, &kv, false, true)
+}
+
+fn test_linearizability_node_added(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new node to the system and run operations during the rebalancing
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+    let nodes = sys.get_node_ids();
+    check_concurrent_operations(&mut sys, &nodes, &mut kv, &mut rand)
+}
+
+fn test_linearizability_node_removed(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // remove a node from the system and run operations during the rebalancing
+    let removed = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+    send_node_removed(&mut sys, &removed);
+    let nodes: Vec<String> = sys
+        .get_node_ids()
+        .into_iter()
+        .filter(|x| *x != removed)
+        .collect();
+    check_concurrent_operations(&mut sys, &nodes, &mut kv, &mut rand)
+}
+
+fn test_linearizability_node_replaced(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new node and remove old one at once, then run operations during the rebalancing
+    let removed = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+    send_node_removed(&mut sys, &removed);
+    let nodes: Vec<String> = sys
+        .get_node_ids()
+        .into_iter()
+        .filter(|x| *x != removed)
+        .collect();
+    check_concurrent_operations(&mut sys, &nodes, &mut kv, &mut rand)
+}
+
+// Adds and removes nodes one by one measuring the cost of each rebalancing
+fn test_migration_traffic(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids().clone();
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add 2 new nodes and then remove 2 old nodes
+    let mut changes = Vec::new();
+    for i in 0..2 {
+        changes.push((format!("{}", config.node_count + i), true));
+    }
+    for i in 0..2 {
+        changes.push((format!("{}", i), false));
+    }
+    for (node, added) in changes {
+        let dist_before = key_distribution(&mut sys)?;
+        let traffic_before = sys.get_network_traffic();
+        let messages_before = sys.get_network_message_count();
+        if added {
+            add_node(&node, &mut sys, config);
+            send_node_added(&mut sys, &node);
+            nodes.push(node);
+        } else {
+            send_node_removed(&mut sys, &node);
+            nodes.retain(|x| *x != node);
+        }
+        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+        let dist_after = key_distribution(&mut sys)?;
+        check_migration_traffic(
+            &kv,
+            &dist_before,
+            &dist_after,
+            sys.get_network_traffic() - traffic_before,
+            sys.get_network_message_count() - messages_before,
+        )?;
+    }
+
+    check(&mut sys, &nodes, &kv, false, false)
+}
+
+// Changes membership as given and checks that no keys are lost or duplicated after rebalancing
+fn test_membership_changes(
+    config: &TestConfig,
+    added_count: u32,
+    removed_count: u32,
+    steps_between: u32,
+) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids().clone();
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // interleave additions of new nodes and removals of random old nodes
+    let mut removed: Vec<String> = nodes
+        .choose_multiple(&mut rand, removed_count as usize)
+        .cloned()
+        .collect();
+    let mut changes = Vec::new();
+    for i in 0..added_count.max(removed_count) {
+        if i < added_count {
+            changes.push((format!("{}", config.node_count + i), true));
+        }
+        if let Some(node) = removed.pop() {
+            changes.push((node, false));
+        }
+    }
+    apply_membership_changes(&mut sys, config, &mut nodes, &changes, steps_between);
+
+    // run the system until key the distribution is stabilized
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 2000)?;
+
+    check(&mut sys, &nodes, &kv, false, true)
+}
+
+fn test_nodes_added_at_once(config: &TestConfig) -> TestResult {
+    test_membership_changes(config, (config.node_count / 2).max(1), 0, 0)
+}
+
+fn test_nodes_removed_at_once(config: &TestConfig) -> TestResult {
+    test_membership_changes(config, 0, (config.node_count / 2).max(1), 0)
+}
+
+fn test_nodes_added_and_removed_at_once(config: &TestConfig) -> TestResult {
+    let count = (config.node_count / 3).max(1);
+    test_membership_changes(config, count, count, 0)
+}
+
+fn test_nodes_changed_in_quick_succession(config: &TestConfig) -> TestResult {
+    let count = (config.node_count / 3).max(1);
+    test_membership_changes(config, count, count, 10)
+}
+
+fn test_scan(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    let nodes = sys.get_node_ids();
+    check_scans(&mut sys, &nodes, &kv, &mut rand)?;
+
+    // delete some keys and check that they are not returned
+    let mut keys: Vec<String> = kv.keys().cloned().collect();
+    keys.sort();
+    for k in keys.choose_multiple(&mut rand, 100) {
+        let node = nodes.choose(&mut rand).unwrap();
+        let v = kv.remove(k);
+        check_delete(&mut sys, node, k, v.as_deref(), 100)?;
+    }
+    check_scans(&mut sys, &nodes, &kv, &mut rand)
+}
+
+fn test_scan_node_added(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new node to the system
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+
+    // run the system until key the distribution is stabilized
+    let nodes = sys.get_node_ids();
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+
+    check_scans(&mut sys, &nodes, &kv, &mut rand)
+}
+
+// Scans are issued right after the membership change, before the distribution is stabilized
+fn test_scan_rebalancing(config: &TestConfig, node_added: bool) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    let nodes: Vec<String> = if node_added {
+        let added = format!("{}", sys.get_node_ids().len());
+        add_node(&added, &mut sys, config);
+        send_node_added(&mut sys, &added);
+        sys.get_node_ids()
+    } else {
+        let removed = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        send_node_removed(&mut sys, &removed);
+        sys.get_node_ids()
+            .into_iter()
+            .filter(|x| *x != removed)
+            .collect()
+    };
+    check_scans(&mut sys, &nodes, &kv, &mut rand)?;
+
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    check_scans(&mut sys, &nodes, &kv, &mut rand)
+}
+
+fn test_scan_rebalancing_node_added(config: &TestConfig) -> TestResult {
+    test_scan_rebalancing(config, true)
+}
+
+fn test_scan_rebalancing_node_removed(config: &TestConfig) -> TestResult {
+    test_scan_rebalancing(config, false)
+}
+
+fn test_cas(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+
+    for _ in 0..10 {
+        let key = random_string(8, &mut rand).to_uppercase();
+        let v1 = random_string(8, &mut rand);
+        let v2 = random_string(8, &mut rand);
+        let mut random_node = || nodes.choose(&mut rand).unwrap().clone();
+
+        // create missing record
+        check_cas(
+            &mut sys,
+            &random_node(),
+            &key,
+            Some(&v1),
+            &v2,
+            (false, None),
+            100,
+        )?;
+        check_cas(
+            &mut sys,
+            &random_node(),
+            &key,
+            None,
+            &v1,
+            (true, Some(&v1)),
+            100,
+        )?;
+        check_cas(
+            &mut sys,
+            &random_node(),
+            &key,
+            None,
+            &v2,
+            (false, Some(&v1)),
+            100,
+        )?;
+        // update existing record
+        check_cas(
+            &mut sys,
+            &random_node(),
+            &key,
+            Some(&v2),
+            &v2,
+            (false, Some(&v1)),
+            100,
+        )?;
+        check_cas(
+            &mut sys,
+            &random_node(),
+            &key,
+            Some(&v1),
+            &v2,
+            (true, Some(&v2)),
+            100,
+        )?;
+        check_get(&mut sys, &random_node(), &key, Some(&v2), 100)?;
+        // deleted record is missing again
+        check_delete(&mut sys, &random_node(), &key, Some(&v2), 100)?;
+        check_cas(
+            &mut sys,
+            &random_node(),
+            &key,
+            Some(&v2),
+            &v1,
+            (false, None),
+            100,
+        )?;
+    }
+    Ok(true)
+}
+
+const CAS_KEYS: usize = 10;
+const CAS_ROUNDS: usize = 5;
+
+fn check_concurrent_cas_rounds(
+    sys: &mut System<JsonMessage>,
+    nodes: &[String],
+    rand: &mut Pcg64,
+) -> TestResult {
+    for _ in 0..CAS_KEYS {
+        let key = random_string(8, rand).to_uppercase();
+        let mut value: Option<String> = None;
+        for _ in 0..CAS_ROUNDS {
+            value = Some(run_concurrent_cas(
+                sys,
+                nodes,
+                &key,
+                value.as_deref(),
+                rand,
+            )?);
+        }
+        let node = nodes.choose(rand).unwrap();
+        check_get(sys, node, &key, value.as_deref(), 100)?;
+    }
+    Ok(true)
+}
+
+fn test_cas_concurrent(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+    check_concurrent_cas_rounds(&mut sys, &nodes, &mut rand)
+}
+
+// Concurrent CAS operations are issued right after the node addition, before the distribution is stabilized
+fn test_cas_concurrent_rebalancing(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert random key-value pairs
+    let keys_count = 1000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new node to the system
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+
+    // run CAS on existing keys which can be migrating
+    let nodes = sys.get_node_ids();
+    let mut keys: Vec<String> = kv.keys().cloned().collect();
+    keys.sort();
+    for key in keys.choose_multiple(&mut rand, CAS_KEYS) {
+        let mut value = kv.get(key).cloned();
+        for _ in 0..CAS_ROUNDS {
+            value = Some(run_concurrent_cas(
+                &mut sys,
+                &nodes,
+                key,
+                value.as_deref(),
+                &mut rand,
+            )?);
+        }
+        kv.insert(key.clone(), value.unwrap());
+    }
+
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    check(&mut sys, &nodes, &kv, false, false)?;
+    check_concurrent_cas_rounds(&mut sys, &nodes, &mut rand)
 }
 
-fn test_scale_up_down(config: &TestConfig) -> TestResult {
+const TTL: f64 = 100.;
+const TTL_MEMORY: f64 = 1000.;
+// Allowed delay between the expiration time and the actual removal of a record
+const TTL_MAX_DELAY: f64 = 1.;
+
+// Inserts random key-value pairs with the given TTL, returns the time of the last PUT completion
+fn put_with_ttl(
+    sys: &mut System<JsonMessage>,
+    kv: &mut HashMap<String, String>,
+    keys_count: usize,
+    ttl: Option<f64>,
+    rand: &mut Pcg64,
+) -> Result<f64, String> {
+    for _ in 0..keys_count {
+        let k = random_string(8, rand).to_uppercase();
+        let v = random_string(8, rand);
+        let node = sys.get_node_ids().choose(rand).unwrap().clone();
+        check_put_with_ttl(sys, &node, &k, &v, ttl, 100)?;
+        kv.insert(k, v);
+    }
+    Ok(sys.time())
+}
+
+fn check_expired(
+    sys: &mut System<JsonMessage>,
+    expired: &HashMap<String, String>,
+    rand: &mut Pcg64,
+) -> TestResult {
+    for k in expired.keys() {
+        let node = sys.get_node_ids().choose(rand).unwrap().clone();
+        check_get(sys, &node, k, None, 100)?;
+    }
+    Ok(true)
+}
+
+fn test_ttl(config: &TestConfig) -> TestResult {
     let mut sys = build_system(config, false);
     let mut rand = Pcg64::seed_from_u64(config.seed);
-    let mut nodes = sys.get_node_ids().clone();
 
-    // insert random key-value pairs
-    let keys_count = 1000;
-    let mut kv = HashMap::new();
-    for _ in 0..keys_count {
-        let k = random_string(8, &mut rand).to_uppercase();
-        let v = random_string(8, &mut rand);
+    // insert keys without TTL and then keys with TTL
+    let mut persistent = HashMap::new();
+    put_with_ttl(&mut sys, &mut persistent, 100, None, &mut rand)?;
+    let ttl_start = sys.time();
+    let mut expiring = HashMap::new();
+    put_with_ttl(&mut sys, &mut expiring, 100, Some(TTL), &mut rand)?;
+
+    // PUT overrides TTL: make some persistent keys expiring and vice versa
+    let mut keys: Vec<String> = persistent.keys().cloned().collect();
+    keys.sort();
+    for k in keys.choose_multiple(&mut rand, 10) {
+        let v = persistent.remove(k).unwrap();
         let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
-        check_put(&mut sys, &node, &k, &v, 100)?;
-        kv.insert(k, v);
+        check_put_with_ttl(&mut sys, &node, k, &v, Some(TTL), 100)?;
+        expiring.insert(k.clone(), v);
+    }
+    let mut keys: Vec<String> = expiring.keys().cloned().collect();
+    keys.sort();
+    for k in keys.choose_multiple(&mut rand, 10) {
+        let v = expiring.remove(k).unwrap();
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, k, &v, 100)?;
+        persistent.insert(k.clone(), v);
     }
+    let ttl_end = sys.time();
 
-    // add new N nodes to the system
-    for i in 0..config.node_count {
-        let added = format!("{}", config.node_count + i);
-        add_node(&added, &mut sys, config);
-        send_node_added(&mut sys, &added);
-        nodes.push(added);
-        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    // all keys are present before the expiration
+    let nodes = sys.get_node_ids();
+    let mut all = persistent.clone();
+    all.extend(expiring.clone());
+    check(&mut sys, &nodes, &all, false, false)?;
+    assume!(
+        sys.time() < ttl_start + TTL,
+        "Test took too long to check keys before the expiration"
+    )?;
+
+    // run the system until all expiring keys are expired
+    sys.step_for_duration(ttl_end + TTL + TTL_MAX_DELAY - sys.time());
+    check_expired(&mut sys, &expiring, &mut rand)?;
+    check(&mut sys, &nodes, &persistent, true, false)
+}
+
+fn test_ttl_node_added(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    // insert keys without TTL and then keys with TTL
+    let mut persistent = HashMap::new();
+    put_with_ttl(&mut sys, &mut persistent, 100, None, &mut rand)?;
+    let ttl_start = sys.time();
+    let mut expiring = HashMap::new();
+    let ttl_end = put_with_ttl(&mut sys, &mut expiring, 100, Some(TTL), &mut rand)?;
+
+    // add new node to the system and wait until expiring keys are migrated
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+    let nodes = sys.get_node_ids();
+    let mut all = persistent.clone();
+    all.extend(expiring.clone());
+    step_until_stabilized(&mut sys, &nodes, all.len() as u64, 100, 1000)?;
+    check(&mut sys, &nodes, &all, false, false)?;
+    assume!(
+        sys.time() < ttl_start + TTL,
+        "Test took too long to check keys before the expiration"
+    )?;
+
+    // migrated keys should expire at the original time
+    sys.step_for_duration(ttl_end + TTL + TTL_MAX_DELAY - sys.time());
+    check_expired(&mut sys, &expiring, &mut rand)?;
+    check(&mut sys, &nodes, &persistent, true, false)
+}
+
+fn total_max_size(sys: &mut System<JsonMessage>) -> u64 {
+    let mut total_mem_size = 0;
+    for node in sys.get_node_ids() {
+        total_mem_size += sys.get_max_size(&node)
+    }
+    total_mem_size
+}
+
+fn test_ttl_memory(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, true);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let keys_count = 1000;
+    let initial_mem_size = total_max_size(&mut sys);
+
+    // insert expiring keys
+    let mut expiring = HashMap::new();
+    let ttl_end = put_with_ttl(
+        &mut sys,
+        &mut expiring,
+        keys_count,
+        Some(TTL_MEMORY),
+        &mut rand,
+    )?;
+    let first_mem_size = total_max_size(&mut sys);
+
+    // wait until keys are expired and insert the same number of new keys,
+    // the max memory size should not grow much if the expired records are freed
+    sys.step_for_duration(ttl_end + TTL_MEMORY + TTL_MAX_DELAY - sys.time());
+    check_expired(&mut sys, &expiring, &mut rand)?;
+    let mut kv = HashMap::new();
+    put_with_ttl(&mut sys, &mut kv, keys_count, None, &mut rand)?;
+    let second_mem_size = total_max_size(&mut sys);
+
+    let first_growth = first_mem_size.saturating_sub(initial_mem_size);
+    let second_growth = second_mem_size.saturating_sub(first_mem_size);
+    println!(
+        "Mem size growth: {} after first insert, {} after expiration and second insert",
+        first_growth, second_growth
+    );
+    assume!(
+        second_growth as f64 <= 0.5 * first_growth as f64,
+        "Memory used by expired records is not freed"
+    )?;
+    let nodes = sys.get_node_ids();
+    check(&mut sys, &nodes, &kv, false, false)
+}
+
+const BATCH_SIZE: usize = 100;
+
+fn test_multi_get_put(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+    let mut kv = HashMap::new();
+
+    // insert batches of random key-value pairs from random nodes
+    for _ in 0..10 {
+        let mut records = BTreeMap::new();
+        for _ in 0..BATCH_SIZE {
+            let k = random_string(8, &mut rand).to_uppercase();
+            let v = random_string(8, &mut rand);
+            records.insert(k, v);
+        }
+        let node = nodes.choose(&mut rand).unwrap();
+        check_multi_put(&mut sys, node, &records, 1000)?;
+        kv.extend(records);
     }
 
+    // records should be stored as if inserted by single PUTs
     check(&mut sys, &nodes, &kv, false, false)?;
 
-    // remove new N nodes
-    for i in 0..config.node_count {
-        let removed = format!("{}", config.node_count + i);
-        send_node_removed(&mut sys, &removed);
-        nodes.remove(config.node_count as usize);
-        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    // read batches of existing and missing keys from random nodes
+    let mut keys: Vec<String> = kv.keys().cloned().collect();
+    keys.sort();
+    for _ in 0..10 {
+        let mut batch: Vec<String> = keys
+            .choose_multiple(&mut rand, BATCH_SIZE * 9 / 10)
+            .cloned()
+            .collect();
+        for _ in 0..BATCH_SIZE / 10 {
+            batch.push(random_string(8, &mut rand).to_uppercase());
+        }
+        let expected: BTreeMap<String, Option<String>> = batch
+            .iter()
+            .map(|k| (k.clone(), kv.get(k).cloned()))
+            .collect();
+        let node = nodes.choose(&mut rand).unwrap();
+        check_multi_get(&mut sys, node, &batch, &expected, 1000)?;
     }
 
+    // overwrite some of the existing keys
+    let mut records = BTreeMap::new();
+    for k in keys.choose_multiple(&mut rand, BATCH_SIZE) {
+        records.insert(k.clone(), random_string(8, &mut rand));
+    }
+    let node = nodes.choose(&mut rand).unwrap();
+    check_multi_put(&mut sys, node, &records, 1000)?;
+    let batch: Vec<String> = records.keys().cloned().collect();
+    let expected: BTreeMap<String, Option<String>> = records
+        .iter()
+        .map(|(k, v)| (k.clone(), Some(v.clone())))
+        .collect();
+    kv.extend(records);
+    let node = nodes.choose(&mut rand).unwrap();
+    check_multi_get(&mut sys, node, &batch, &expected, 1000)?;
     check(&mut sys, &nodes, &kv, false, false)
 }
 
-fn test_distribution(config: &TestConfig) -> TestResult {
+fn test_multi_batching(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+
+    let mut single_records = BTreeMap::new();
+    let mut batch_records = BTreeMap::new();
+    for _ in 0..BATCH_SIZE {
+        single_records.insert(
+            random_string(8, &mut rand).to_uppercase(),
+            random_string(8, &mut rand),
+        );
+        batch_records.insert(
+            random_string(8, &mut rand).to_uppercase(),
+            random_string(8, &mut rand),
+        );
+    }
+
+    // writes
+    let messages_before = sys.get_network_message_count();
+    for (k, v) in single_records.iter() {
+        check_put(&mut sys, &node, k, v, 100)?;
+    }
+    let single_put_messages = sys.get_network_message_count() - messages_before;
+    let messages_before = sys.get_network_message_count();
+    check_multi_put(&mut sys, &node, &batch_records, 1000)?;
+    let multi_put_messages = sys.get_network_message_count() - messages_before;
+
+    // reads
+    let keys: Vec<String> = single_records.keys().cloned().collect();
+    let messages_before = sys.get_network_message_count();
+    for k in keys.iter() {
+        check_get(
+            &mut sys,
+            &node,
+            k,
+            single_records.get(k).map(|v| v.as_str()),
+            100,
+        )?;
+    }
+    let single_get_messages = sys.get_network_message_count() - messages_before;
+    let expected: BTreeMap<String, Option<String>> = single_records
+        .iter()
+        .map(|(k, v)| (k.clone(), Some(v.clone())))
+        .collect();
+    let messages_before = sys.get_network_message_count();
+    check_multi_get(&mut sys, &node, &keys, &expected, 1000)?;
+    let multi_get_messages = sys.get_network_message_count() - messages_before;
+
+    println!("\nNetwork messages for {} keys:", BATCH_SIZE);
+    println!("  - PUT: {}", single_put_messages);
+    println!("  - MULTI_PUT: {}", multi_put_messages);
+    println!("  - GET: {}", single_get_messages);
+    println!("  - MULTI_GET: {}", multi_get_messages);
+    assume!(
+        multi_put_messages < single_put_messages,
+        "MULTI_PUT does not use fewer messages than single PUTs"
+    )?;
+    assume!(
+        multi_get_messages < single_get_messages,
+        format!(
+            "MULTI_GET does not use fewer messages than single GETs ({} vs {})",
+            multi_get_messages, single_get_messages
+        )
+    )?;
+    Ok(true)
+}
+
+fn test_placement(config: &TestConfig) -> TestResult {
     let mut sys = build_system(config, false);
     let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids().clone();
 
     // insert random key-value pairs
-    let keys_count = 10000;
+    let keys_count = 1000;
     let mut kv = HashMap::new();
     for _ in 0..keys_count {
         let k = random_string(8, &mut rand).to_uppercase();
@@ -687,11 +3003,27 @@ fn test_distribution(config: &TestConfig) -> TestResult {
         kv.insert(k, v);
     }
 
-    let nodes = sys.get_node_ids();
-    check(&mut sys, &nodes, &kv, false, true)
+    let scheme = get_placement_scheme(&mut sys, &nodes[0])?;
+    check_placement(&mut sys, &nodes, scheme)?;
+
+    // add new node to the system
+    let added = format!("{}", sys.get_node_ids().len());
+    add_node(&added, &mut sys, config);
+    send_node_added(&mut sys, &added);
+    nodes.push(added);
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    check_placement(&mut sys, &nodes, scheme)?;
+
+    // remove a node from the system
+    let removed = nodes.remove(rand.gen_range(0..nodes.len()));
+    send_node_removed(&mut sys, &removed);
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    check_placement(&mut sys, &nodes, scheme)
 }
 
-fn test_distribution_node_added(config: &TestConfig) -> TestResult {
+const NODE_WEIGHTS: [f64; 3] = [0.5, 2., 3.];
+
+fn test_weighted_node_added(config: &TestConfig) -> TestResult {
     let mut sys = build_system(config, false);
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut kv = HashMap::new();
@@ -706,24 +3038,66 @@ fn test_distribution_node_added(config: &TestConfig) -> TestResult {
         kv.insert(k, v);
     }
     let dist_before = key_distribution(&mut sys)?;
+    let weights_before: HashMap<String, f64> =
+        sys.get_node_ids().into_iter().map(|n| (n, 1.)).collect();
 
-    // add new node to the system
+    // add new node with weight 3 to the system
     let added = format!("{}", sys.get_node_ids().len());
     add_node(&added, &mut sys, config);
-    send_node_added(&mut sys, &added);
+    send_node_added_with_weight(&mut sys, &added, Some(3.));
+    let mut weights_after = weights_before.clone();
+    weights_after.insert(added.clone(), 3.);
 
     // run the system until key the distribution is stabilized
     let nodes = sys.get_node_ids();
     step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
     let dist_after = key_distribution(&mut sys)?;
 
-    let target_moved_keys = (keys_count as f64 / nodes.len() as f64).round() as u64;
+    let target_moved_keys = optimal_moved_keys(keys_count, &weights_before, &weights_after);
     check_moved_keys(&mut sys, &dist_before, &dist_after, target_moved_keys)?;
 
-    check(&mut sys, &nodes, &kv, false, true)
+    check_with_weights(&mut sys, &nodes, &kv, false, true, &weights_after)
 }
 
-fn test_distribution_node_removed(config: &TestConfig) -> TestResult {
+fn test_weighted_nodes(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids().clone();
+    let mut weights = HashMap::new();
+
+    // insert random key-value pairs
+    let keys_count = 10000;
+    let mut kv = HashMap::new();
+    for _ in 0..keys_count {
+        let k = random_string(8, &mut rand).to_uppercase();
+        let v = random_string(8, &mut rand);
+        let node = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+        check_put(&mut sys, &node, &k, &v, 100)?;
+        kv.insert(k, v);
+    }
+
+    // add new N/2 nodes with different weights to the system
+    for i in 0..(config.node_count / 2).max(1) {
+        let added = format!("{}", config.node_count + i);
+        let weight = NODE_WEIGHTS[i as usize % NODE_WEIGHTS.len()];
+        add_node(&added, &mut sys, config);
+        send_node_added_with_weight(&mut sys, &added, Some(weight));
+        weights.insert(added.clone(), weight);
+        nodes.push(added);
+        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    }
+
+    check_with_weights(&mut sys, &nodes, &kv, false, true, &weights)?;
+
+    // remove one of the old nodes
+    let removed = nodes.remove(0);
+    send_node_removed(&mut sys, &removed);
+    step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+
+    check_with_weights(&mut sys, &nodes, &kv, false, true, &weights)
+}
+
+fn test_weighted_node_changed(config: &TestConfig) -> TestResult {
     let mut sys = build_system(config, false);
     let mut rand = Pcg64::seed_from_u64(config.seed);
     let mut kv = HashMap::new();
@@ -738,26 +3112,105 @@ fn test_distribution_node_removed(config: &TestConfig) -> TestResult {
         kv.insert(k, v);
     }
     let dist_before = key_distribution(&mut sys)?;
+    let weights_before: HashMap<String, f64> =
+        sys.get_node_ids().into_iter().map(|n| (n, 1.)).collect();
 
-    // remove a node from the system
-    let removed = sys.get_node_ids().choose(&mut rand).unwrap().clone();
-    let count = count_records(&mut sys, &removed)?;
-    assume!(count > 0, "Node stores no records, bad distribution")?;
-    send_node_removed(&mut sys, &removed);
+    // increase the weight of a node
+    let changed = sys.get_node_ids().choose(&mut rand).unwrap().clone();
+    send_node_added_with_weight(&mut sys, &changed, Some(2.));
+    let mut weights_after = weights_before.clone();
+    weights_after.insert(changed.clone(), 2.);
 
     // run the system until key the distribution is stabilized
-    let nodes: Vec<String> = sys
-        .get_node_ids()
-        .into_iter()
-        .filter(|x| *x != removed)
-        .collect();
+    let nodes = sys.get_node_ids();
     step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
     let dist_after = key_distribution(&mut sys)?;
 
-    let target_moved_keys = (keys_count as f64 / (nodes.len() + 1) as f64).round() as u64;
+    let target_moved_keys = optimal_moved_keys(keys_count, &weights_before, &weights_after);
     check_moved_keys(&mut sys, &dist_before, &dist_after, target_moved_keys)?;
 
-    check(&mut sys, &nodes, &kv, false, true)
+    check_with_weights(&mut sys, &nodes, &kv, false, true, &weights_after)
+}
+
+fn test_load_uniform(config: &TestConfig) -> TestResult {
+    let imbalance = run_skewed_workload(config, KeyPopularity::Uniform)?;
+    assume!(
+        imbalance <= LOAD_MAX_IMBALANCE,
+        format!(
+            "Request load imbalance is above {} for uniform workload",
+            LOAD_MAX_IMBALANCE
+        )
+    )
+}
+
+// Skewed workloads only report load imbalance, since it can not be fixed by keys distribution alone
+fn test_load_zipf(config: &TestConfig) -> TestResult {
+    run_skewed_workload(config, KeyPopularity::Zipf(config.zipf_exponent))?;
+    Ok(true)
+}
+
+fn test_load_hot_key(config: &TestConfig) -> TestResult {
+    run_skewed_workload(config, KeyPopularity::HotKey(0.5))?;
+    Ok(true)
+}
+
+fn test_routing_hops(config: &TestConfig) -> TestResult {
+    let mut max_hops = 0;
+    println!("\nHops per operation:");
+    for scale in ROUTING_SCALES {
+        let mut scaled_config = *config;
+        scaled_config.node_count = config.node_count * scale;
+        let mut sys = build_system(&scaled_config, false);
+        let mut rand = Pcg64::seed_from_u64(config.seed);
+
+        let mut kv = HashMap::new();
+        let put_hops = put_counting_hops(&mut sys, &mut kv, ROUTING_KEYS, &mut rand)?;
+        let get_hops = get_counting_hops(&mut sys, &kv, &mut rand)?;
+        let (put_avg, put_max) = hop_stats(&put_hops);
+        let (get_avg, get_max) = hop_stats(&get_hops);
+        println!(
+            "  - {} nodes: PUT avg {:.2} max {}, GET avg {:.2} max {}",
+            scaled_config.node_count, put_avg, put_max, get_avg, get_max
+        );
+        max_hops = max_hops.max(put_max).max(get_max);
+    }
+    check_max_hops(max_hops)
+}
+
+fn test_routing_hops_node_added(config: &TestConfig) -> TestResult {
+    let mut config = *config;
+    config.trace_hops = true;
+    let config = &config;
+    let mut sys = build_system(config, false);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let mut nodes = sys.get_node_ids();
+
+    let mut kv = HashMap::new();
+    put_counting_hops(&mut sys, &mut kv, ROUTING_KEYS, &mut rand)?;
+
+    // add new N nodes to the system one by one,
+    // requests sent via nodes with stale routing tables could be forwarded several times
+    let mut max_hops = 0;
+    println!("\nHops per operation:");
+    for i in 0..config.node_count {
+        let added = format!("{}", config.node_count + i);
+        add_node(&added, &mut sys, config);
+        send_node_added(&mut sys, &added);
+        nodes.push(added);
+
+        // measure hops while the keys are being moved to the added node
+        let get_hops = get_counting_key_hops(&mut sys, &kv, &mut rand)?;
+        let (get_avg, get_max) = hop_stats(&get_hops);
+        println!(
+            "  - {} nodes: GET avg {:.2} max {}",
+            nodes.len(),
+            get_avg,
+            get_max
+        );
+        max_hops = max_hops.max(get_max);
+        step_until_stabilized(&mut sys, &nodes, kv.len() as u64, 100, 1000)?;
+    }
+    check_max_hops(max_hops)
 }
 
 // CLI -----------------------------------------------------------------------------------------------------------------
@@ -786,6 +3239,54 @@ struct Args {
     #[clap(long, short, default_value = "123")]
     seed: u64,
 
+    /// Exponent of Zipf distribution of key popularity in LOAD ZIPF test
+    #[clap(long, default_value = "1.0")]
+    zipf_exponent: f64,
+
+    /// Run tests checking keys placement for the scheme declared by solution
+    #[clap(long)]
+    oracle: bool,
+
+    /// Run tests with client operations during rebalancing
+    #[clap(long)]
+    linearizability: bool,
+
+    /// Run tests with node restarts using durable storage
+    #[clap(long)]
+    storage: bool,
+
+    /// Run tests with nodes of different weights
+    #[clap(long)]
+    weighted: bool,
+
+    /// Run tests measuring node load under skewed workloads
+    #[clap(long)]
+    load: bool,
+
+    /// Run tests of SCAN operation
+    #[clap(long)]
+    scan: bool,
+
+    /// Run tests of batch operations
+    #[clap(long)]
+    multi: bool,
+
+    /// Run test of network traffic during rebalancing
+    #[clap(long)]
+    traffic: bool,
+
+    /// Run tests of CAS operation
+    #[clap(long)]
+    cas: bool,
+
+    /// Run tests of records expiration
+    #[clap(long)]
+    ttl: bool,
+
+    /// Run tests of request routing hops
+    #[clap(long)]
+    hops: bool,
+
     /// Path to dslib directory
     #[clap(long = "lib", short = 'l', default_value = "../../dslib")]
     dslib_path: String,
@@ -805,9 +3306,14 @@ fn main() {
         node_factory: &node_factory,
         node_count: args.node_count,
         seed: args.seed,
+        zipf_exponent: args.zipf_exponent,
+        trace_hops: false,
+        with_storage: false,
     };
     let mut single_config = config.clone();
     single_config.node_count = 1;
+    let mut storage_config = config;
+    storage_config.with_storage = true;
     let mut tests = TestSuite::new();
 
     tests.add("SINGLE NODE", test_single_node, single_config);
@@ -821,6 +3327,16 @@ fn main() {
         test_node_removed_after_crash,
         config,
     );
+    tests.add(
+        "SOURCE CRASH DURING REBALANCING",
+        test_source_crash_during_rebalancing,
+        config,
+    );
+    tests.add(
+        "DESTINATION CRASH DURING REBALANCING",
+        test_destination_crash_during_rebalancing,
+        config,
+    );
     tests.add("MIGRATION", test_migration, config);
     tests.add("SCALE UP DOWN", test_scale_up_down, config);
     tests.add("DISTRIBUTION", test_distribution, config);
@@ -834,6 +3350,108 @@ fn main() {
         test_distribution_node_removed,
         config,
     );
+    tests.add("NODES ADDED AT ONCE", test_nodes_added_at_once, config);
+    tests.add("NODES REMOVED AT ONCE", test_nodes_removed_at_once, config);
+    tests.add(
+        "NODES ADDED AND REMOVED AT ONCE",
+        test_nodes_added_and_removed_at_once,
+        config,
+    );
+    tests.add(
+        "NODES CHANGED IN QUICK SUCCESSION",
+        test_nodes_changed_in_quick_succession,
+        config,
+    );
+    if args.oracle {
+        tests.add("PLACEMENT", test_placement, config);
+    }
+    if args.storage {
+        tests.add(
+            "STORAGE NODE RESTARTED",
+            test_storage_node_restarted,
+            storage_config,
+        );
+        tests.add(
+            "STORAGE CLUSTER RESTARTED",
+            test_storage_cluster_restarted,
+            storage_config,
+        );
+        tests.add(
+            "STORAGE RESTARTED AFTER REBALANCING",
+            test_storage_restarted_after_rebalancing,
+            storage_config,
+        );
+    }
+    if args.linearizability {
+        tests.add(
+            "LINEARIZABILITY NODE ADDED",
+            test_linearizability_node_added,
+            config,
+        );
+        tests.add(
+            "LINEARIZABILITY NODE REMOVED",
+            test_linearizability_node_removed,
+            config,
+        );
+        tests.add(
+            "LINEARIZABILITY NODE REPLACED",
+            test_linearizability_node_replaced,
+            config,
+        );
+    }
+    if args.weighted {
+        tests.add("WEIGHTED NODE ADDED", test_weighted_node_added, config);
+        tests.add("WEIGHTED NODES", test_weighted_nodes, config);
+        tests.add("WEIGHTED NODE CHANGED", test_weighted_node_changed, config);
+    }
+    if args.load {
+        tests.add("LOAD UNIFORM", test_load_uniform, config);
+        tests.add("LOAD ZIPF", test_load_zipf, config);
+        tests.add("LOAD HOT KEY", test_load_hot_key, config);
+    }
+    if args.scan {
+        tests.add("SCAN", test_scan, config);
+        tests.add("SCAN NODE ADDED", test_scan_node_added, config);
+        tests.add(
+            "SCAN REBALANCING NODE ADDED",
+            test_scan_rebalancing_node_added,
+            config,
+        );
+        tests.add(
+            "SCAN REBALANCING NODE REMOVED",
+            test_scan_rebalancing_node_removed,
+            config,
+        );
+    }
+    if args.multi {
+        tests.add("MULTI GET PUT", test_multi_get_put, config);
+        tests.add("MULTI BATCHING", test_multi_batching, config);
+    }
+    if args.traffic {
+        tests.add("MIGRATION TRAFFIC", test_migration_traffic, config);
+    }
+    if args.cas {
+        tests.add("CAS", test_cas, config);
+        tests.add("CAS CONCURRENT", test_cas_concurrent, config);
+        tests.add(
+            "CAS CONCURRENT REBALANCING",
+            test_cas_concurrent_rebalancing,
+            config,
+        );
+    }
+    if args.ttl {
+        tests.add("TTL", test_ttl, config);
+        tests.add("TTL NODE ADDED", test_ttl_node_added, config);
+        tests.add("TTL MEMORY", test_ttl_memory, config);
+    }
+    if args.hops {
+        tests.add("ROUTING HOPS", test_routing_hops, config);
+        tests.add(
+            "ROUTING HOPS NODE ADDED",
+            test_routing_hops_node_added,
+            config,
+        );
+    }
 
     if args.test.is_none() {
         tests.run();
@@ -1,3 +1,4 @@
+import bisect
 import hashlib
 from dslib import Context, Message, Node
 from typing import List
@@ -6,7 +7,7 @@ def is_newer(context1, value1, context2, value2):
     return context1 > context2 or (context1 == context2 and (value2 is None or (value1 is not None and value1 > value2)))
 
 class RequestContext:
-    def __init__(self, ctx, nodes, node_id, req_id, key, value, quorum, msg_type: str):
+    def __init__(self, ctx, nodes, node_id, req_id, key, value, quorum, msg_type: str, replication_factor=3, vnodes=0):
         self.nodes = nodes
         self.node_id = node_id
         self.key = key
@@ -25,9 +26,11 @@ class RequestContext:
         self.final_context = -1
         self.final_value = ''
         
-        self.replica_idxs = get_key_replicas(self.key, len(self.nodes))
+        preference_list = get_key_preference_list(self.key, len(self.nodes), vnodes)
+        self.replica_idxs = preference_list[:replication_factor]
         self.replicas = [self.nodes[x] for x in self.replica_idxs]
-        self.next_replica_idx = self.replica_idxs[-1]
+        self.extra_replica_idxs = preference_list[replication_factor:]
+        self.next_extra_replica = 0
 
         self.ack_replicas = set()
         self.missing_replicas = set()
@@ -135,12 +138,10 @@ class RequestContext:
         ctx.set_timer(self.timer_name(node), 1)
 
     def send_extra_req(self, ctx):
-        while True:
-            self.next_replica_idx = (self.next_replica_idx + 1) % len(self.nodes)
-            if self.next_replica_idx not in self.replica_idxs:
-                break
-        print('EXTRA {0} {1}->{2}'.format(self.msg_type, self.node_id, self.next_replica_idx))
-        self.send_req(ctx, self.nodes[self.next_replica_idx])
+        idx = self.extra_replica_idxs[self.next_extra_replica % len(self.extra_replica_idxs)]
+        self.next_extra_replica += 1
+        print('EXTRA {0} {1}->{2}'.format(self.msg_type, self.node_id, idx))
+        self.send_req(ctx, self.nodes[idx])
 
     def nack(self, sender, ctx: Context):
         if self.delivered:
@@ -158,9 +159,11 @@ class RequestContext:
                 self.send_extra_req(ctx)
                 
 class StorageNode(Node):
-    def __init__(self, node_id: str, nodes: List[str]):
+    def __init__(self, node_id: str, nodes: List[str], replication_factor: int = 3, vnodes: int = 0):
         self._id = node_id
         self._nodes = nodes
+        self._replication_factor = replication_factor
+        self._vnodes = vnodes
         self._data = {}
 
         self.req_ctr = 0
@@ -169,7 +172,7 @@ class StorageNode(Node):
     def on_local_message(self, msg: Message, ctx: Context):
         # Get key value.
         # Request:
-        #   GET {"key": "some key", "quorum": 1-3}
+        #   GET {"key": "some key", "quorum": 1-N}
         # Response:
         #   GET_RESP {"key": "some key", "value": "value for this key"}
         #   GET_RESP {"key": "some key", "value": null} - if record for this key is not found
@@ -178,11 +181,12 @@ class StorageNode(Node):
 
             req_id = str(self._id) + ":" + str(self.req_ctr)
             self.req_ctr += 1
-            self.requests[req_id] = RequestContext(ctx, self._nodes, self._id, req_id, key, None, msg['quorum'], 'GET')
+            self.requests[req_id] = RequestContext(ctx, self._nodes, self._id, req_id, key, None, msg['quorum'], 'GET',
+                                                  self._replication_factor, self._vnodes)
 
         # Store (key, value) record
         # Request:
-        #   PUT {"key": "some key", "value: "some value", "quorum": 1-3}
+        #   PUT {"key": "some key", "value: "some value", "quorum": 1-N}
         # Response:
         #   PUT_RESP {"key": "some key", "value: "some value"}
         elif msg.type == 'PUT':
@@ -191,11 +195,12 @@ class StorageNode(Node):
 
             req_id = str(self._id) + ":" + str(self.req_ctr)
             self.req_ctr += 1
-            self.requests[req_id] = RequestContext(ctx, self._nodes, self._id, req_id, key, value, msg['quorum'], 'PUT')
+            self.requests[req_id] = RequestContext(ctx, self._nodes, self._id, req_id, key, value, msg['quorum'], 'PUT',
+                                                  self._replication_factor, self._vnodes)
 
         # Delete value for the key
         # Request:
-        #   DELETE {"key": "some key", "quorum": 1-3}
+        #   DELETE {"key": "some key", "quorum": 1-N}
         # Response:
         #   DELETE_RESP {"key": "some key", "value": "some value"}
         elif msg.type == 'DELETE':
@@ -203,7 +208,8 @@ class StorageNode(Node):
 
             req_id = str(self._id) + ":" + str(self.req_ctr)
             self.req_ctr += 1
-            self.requests[req_id] = RequestContext(ctx, self._nodes, self._id, req_id, key, None, msg['quorum'], 'DELETE')
+            self.requests[req_id] = RequestContext(ctx, self._nodes, self._id, req_id, key, None, msg['quorum'], 'DELETE',
+                                                  self._replication_factor, self._vnodes)
 
 
     def on_message(self, msg: Message, sender: str, ctx: Context):
@@ -290,15 +296,28 @@ class StorageNode(Node):
         
 
 
-def get_key_replicas(key: str, node_count: int):
-    replicas = []
-    key_hash = int.from_bytes(hashlib.md5(key.encode('utf8')).digest(), 'little', signed=False)
-    cur = key_hash % node_count
-    for _ in range(3):
-        replicas.append(cur)
-        cur = get_next_replica(cur, node_count)
-    return replicas
+def get_key_replicas(key: str, node_count: int, replication_factor: int = 3, vnodes: int = 0):
+    return get_key_preference_list(key, node_count, vnodes)[:replication_factor]
 
 
-def get_next_replica(i, node_count: int):
-    return (i + 1) % node_count
+# Returns indices of all nodes in order of preference for storing the key:
+# the first replication_factor nodes are key replicas, the rest can be used in sloppy quorum.
+# Without virtual nodes, replicas are consecutive node indices starting from the key hash,
+# otherwise nodes are ordered by walking the ring with vnodes points per node clockwise from the key hash.
+def get_key_preference_list(key: str, node_count: int, vnodes: int = 0):
+    key_hash = get_hash(key)
+    if vnodes == 0:
+        start = key_hash % node_count
+        return [(start + i) % node_count for i in range(node_count)]
+    ring = sorted((get_hash('{}-{}'.format(node, vnode)), node) for node in range(node_count) for vnode in range(vnodes))
+    start = bisect.bisect_left(ring, (key_hash, -1))
+    nodes = []
+    for i in range(len(ring)):
+        node = ring[(start + i) % len(ring)][1]
+        if node not in nodes:
+            nodes.append(node)
+    return nodes
+
+
+def get_hash(s: str):
+    return int.from_bytes(hashlib.md5(s.encode('utf8')).digest(), 'little', signed=False)
@@ -1,3 +1,6 @@
+mod quorum;
+
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::io::Write;
 
@@ -17,6 +20,8 @@ use dslib::pynode::{JsonMessage, PyNodeFactory};
 use dslib::system::System;
 use dslib::test::{TestResult, TestSuite};
 
+use quorum::{check_quorum, QuorumTracer};
+
 // MESSAGES ------------------------------------------------------------------------------------------------------------
 
 #[derive(Serialize)]
@@ -63,6 +68,12 @@ struct TestConfig<'a> {
     node_factory: &'a PyNodeFactory,
     node_count: u32,
     seed: u64,
+    replication_factor: u8,
+    read_quorum: u8,
+    write_quorum: u8,
+    vnodes: u32,
+    check_quorums: bool,
+    clock_drift: f64,
 }
 
 fn init_logger(level: LevelFilter) {
@@ -75,15 +86,35 @@ fn init_logger(level: LevelFilter) {
 fn build_system(config: &TestConfig) -> System<JsonMessage> {
     let mut sys = System::with_seed(config.seed);
     sys.set_delays(0.01, 0.1);
+    quorum::reset();
     let mut node_ids = Vec::new();
     for n in 0..config.node_count {
         node_ids.push(format!("{}", n));
     }
     for node_id in node_ids.iter() {
-        let node = config
-            .node_factory
-            .build(node_id, (node_id, node_ids.clone()), config.seed);
-        sys.add_node(rc!(refcell!(node)));
+        // replication parameters are passed only if they differ from the defaults
+        // to support solutions without their support
+        let node = if config.replication_factor == 3 && config.vnodes == 0 {
+            config
+                .node_factory
+                .build(node_id, (node_id, node_ids.clone()), config.seed)
+        } else {
+            config.node_factory.build(
+                node_id,
+                (
+                    node_id,
+                    node_ids.clone(),
+                    config.replication_factor,
+                    config.vnodes,
+                ),
+                config.seed,
+            )
+        };
+        if config.check_quorums {
+            sys.add_node(rc!(refcell!(QuorumTracer::new(node))));
+        } else {
+            sys.add_node(rc!(refcell!(node)));
+        }
     }
     return sys;
 }
@@ -104,6 +135,7 @@ fn check_get(
     assume_eq!(msg.tip, "GET_RESP")?;
     let data: GetRespMessage = serde_json::from_str(&msg.data).unwrap();
     assume_eq!(data.key, key)?;
+    check_quorum(sys, node, "GET", key)?;
     assume_eq!(data.value, expected)?;
     Ok(true)
 }
@@ -127,6 +159,7 @@ fn check_put(
     assume_eq!(msg.tip, "PUT_RESP")?;
     let data: PutRespMessage = serde_json::from_str(&msg.data).unwrap();
     assume_eq!(data.key, key)?;
+    check_quorum(sys, node, "PUT", key)?;
     assume_eq!(data.value, value)?;
     Ok(true)
 }
@@ -152,6 +185,7 @@ fn check_put_result(
     assume_eq!(msg.tip, "PUT_RESP")?;
     let data: PutRespMessage = serde_json::from_str(&msg.data).unwrap();
     assume_eq!(data.key, key)?;
+    check_quorum(sys, node, "PUT", key)?;
     assume_eq!(data.value, value)?;
     Ok(true)
 }
@@ -178,6 +212,7 @@ fn check_delete(
     assume_eq!(msg.tip, "DELETE_RESP")?;
     let data: DeleteRespMessage = serde_json::from_str(&msg.data).unwrap();
     assume_eq!(data.key, key)?;
+    check_quorum(sys, node, "DELETE", key)?;
     assume_eq!(data.value, expected)?;
     Ok(true)
 }
@@ -199,40 +234,598 @@ fn random_string(length: usize, rand: &mut Pcg64) -> String {
         .collect()
 }
 
-fn key_replicas(key: &str, sys: &System<JsonMessage>) -> Vec<String> {
-    let node_count = sys.node_count();
-    let mut replicas = Vec::new();
-    let hash = md5::compute(key);
-    let hash128 = LittleEndian::read_u128(&hash.0);
-    let mut replica = (hash128 % node_count as u128) as u32;
-    for _ in 0..3 {
-        replicas.push(replica.to_string());
-        replica += 1;
-        if replica == node_count {
-            replica = 0;
+// The same as get_key_replicas() from solution template
+fn placement_hash(s: &str) -> u128 {
+    let hash = md5::compute(s);
+    LittleEndian::read_u128(&hash.0)
+}
+
+// All nodes in order of preference for storing the key: the first replication_factor nodes are key replicas,
+// the rest are used as substitutes in sloppy quorum.
+// Without virtual nodes, replicas are consecutive node indices starting from the key hash,
+// otherwise nodes are ordered by walking the ring with vnodes points per node clockwise from the key hash.
+fn key_preference_list(key: &str, config: &TestConfig) -> Vec<String> {
+    let node_count = config.node_count;
+    let key_hash = placement_hash(key);
+    let mut nodes = Vec::new();
+    if config.vnodes == 0 {
+        let start = (key_hash % node_count as u128) as u32;
+        for i in 0..node_count {
+            nodes.push(((start + i) % node_count).to_string());
+        }
+    } else {
+        let mut ring = Vec::new();
+        for node in 0..node_count {
+            for vnode in 0..config.vnodes {
+                ring.push((placement_hash(&format!("{}-{}", node, vnode)), node));
+            }
+        }
+        ring.sort();
+        let start = ring.partition_point(|(hash, _)| *hash < key_hash);
+        for i in 0..ring.len() {
+            let node = ring[(start + i) % ring.len()].1.to_string();
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
         }
     }
-    replicas
+    nodes
 }
 
-fn key_non_replicas(key: &str, sys: &System<JsonMessage>) -> Vec<String> {
-    let replicas = key_replicas(&key, sys);
-    let mut non_replicas_pre = Vec::new();
-    let mut non_replicas = Vec::new();
-    let mut pre = true;
-    for node in sys.get_node_ids() {
-        if replicas.contains(&node) {
-            pre = false;
-            continue;
+fn key_replicas(key: &str, config: &TestConfig) -> Vec<String> {
+    let mut nodes = key_preference_list(key, config);
+    nodes.truncate(config.replication_factor as usize);
+    nodes
+}
+
+fn key_non_replicas(key: &str, config: &TestConfig) -> Vec<String> {
+    let nodes = key_preference_list(key, config);
+    nodes[config.replication_factor as usize..].to_vec()
+}
+
+// Partitions nodes into two parts: the first two non-replicas with the first replica (and remaining non-replicas),
+// and the third non-replica with the rest of replicas
+fn mixed_partition<'a>(
+    replicas: &'a [String],
+    non_replicas: &'a [String],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let mut part1: Vec<&str> = vec![&non_replicas[0], &non_replicas[1], &replicas[0]];
+    part1.extend(non_replicas[3..].iter().map(|s| &**s));
+    let mut part2: Vec<&str> = vec![&non_replicas[2]];
+    part2.extend(replicas[1..].iter().map(|s| &**s));
+    (part1, part2)
+}
+
+const CHAOS_STEPS: u32 = 100;
+const CHAOS_KEYS: usize = 5;
+const CHAOS_MAX_STEPS: u32 = 1000;
+const CHAOS_HEAL_DELAY: f64 = 5.;
+
+enum ChaosOp {
+    Get,
+    Put(String),
+    Delete,
+}
+
+struct ChaosWrite {
+    value: Option<String>,
+    quorum: u8,
+    invoked: f64,
+    completed: bool,
+}
+
+// Network faults injected by chaos monkey, healthy_since is infinite while any fault is active
+struct ChaosNetwork {
+    disconnected: Vec<String>,
+    partitioned: bool,
+    drop_rate: f64,
+    healthy_since: f64,
+}
+
+impl ChaosNetwork {
+    fn new() -> Self {
+        Self {
+            disconnected: Vec::new(),
+            partitioned: false,
+            drop_rate: 0.,
+            healthy_since: 0.,
         }
-        if pre {
-            non_replicas_pre.push(node);
-        } else {
-            non_replicas.push(node);
+    }
+
+    fn is_healthy(&self) -> bool {
+        self.healthy_since.is_finite()
+    }
+
+    fn heal(&mut self, sys: &mut System<JsonMessage>) {
+        for node in self.disconnected.drain(..) {
+            sys.connect_node(&node);
+        }
+        sys.reset_network();
+        sys.set_drop_rate(0.);
+        self.partitioned = false;
+        self.drop_rate = 0.;
+        sys.step_for_duration(CHAOS_HEAL_DELAY);
+        self.healthy_since = sys.time();
+    }
+}
+
+// Values which can be returned by a read with the given quorum according to LWW:
+// if the read quorum intersects with the quorum of the last completed write made in healthy network
+// (which stayed healthy since then), only the value of this write or later writes can be returned,
+// otherwise any written value is acceptable
+fn acceptable_values(
+    writes: &[ChaosWrite],
+    quorum: u8,
+    replication_factor: u8,
+    healthy_since: f64,
+) -> Vec<Option<String>> {
+    let start = writes
+        .iter()
+        .rposition(|w| {
+            w.completed && w.quorum + quorum > replication_factor && w.invoked >= healthy_since
+        })
+        .unwrap_or(0);
+    writes[start..].iter().map(|w| w.value.clone()).collect()
+}
+
+// Returns the value from response or None if the response is not received
+fn chaos_request(
+    sys: &mut System<JsonMessage>,
+    node: &str,
+    key: &str,
+    op: &ChaosOp,
+    quorum: u8,
+) -> Result<Option<Option<String>>, String> {
+    let msg = match op {
+        ChaosOp::Get => JsonMessage::from("GET", &GetMessage { key, quorum }),
+        ChaosOp::Put(value) => JsonMessage::from("PUT", &PutMessage { key, value, quorum }),
+        ChaosOp::Delete => JsonMessage::from("DELETE", &DeleteMessage { key, quorum }),
+    };
+    sys.send_local(msg, node);
+    let res = sys.step_until_local_message_max_steps(node, CHAOS_MAX_STEPS);
+    if res.is_err() {
+        return Ok(None);
+    }
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    let value = match op {
+        ChaosOp::Get => {
+            assume_eq!(msg.tip, "GET_RESP")?;
+            let data: GetRespMessage = serde_json::from_str(&msg.data).unwrap();
+            assume_eq!(data.key, key)?;
+            check_quorum(sys, node, "GET", key)?;
+            data.value.map(|v| v.to_string())
+        }
+        ChaosOp::Put(_) => {
+            assume_eq!(msg.tip, "PUT_RESP")?;
+            let data: PutRespMessage = serde_json::from_str(&msg.data).unwrap();
+            assume_eq!(data.key, key)?;
+            check_quorum(sys, node, "PUT", key)?;
+            Some(data.value.to_string())
+        }
+        ChaosOp::Delete => {
+            assume_eq!(msg.tip, "DELETE_RESP")?;
+            let data: DeleteRespMessage = serde_json::from_str(&msg.data).unwrap();
+            assume_eq!(data.key, key)?;
+            check_quorum(sys, node, "DELETE", key)?;
+            data.value.map(|v| v.to_string())
+        }
+    };
+    Ok(Some(value))
+}
+
+fn run_chaos_monkey(config: &TestConfig, schedule: &mut Vec<String>) -> TestResult {
+    let mut sys = build_system(config);
+    let nodes = sys.get_node_ids();
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    let mut keys = Vec::new();
+    let mut writes = HashMap::new();
+    for _ in 0..CHAOS_KEYS {
+        let key = random_string(8, &mut rand).to_uppercase();
+        let initial = ChaosWrite {
+            value: None,
+            quorum: config.replication_factor,
+            invoked: 0.,
+            completed: true,
+        };
+        writes.insert(key.clone(), vec![initial]);
+        keys.push(key);
+    }
+    // nodes with not completed requests are not used as clients since their responses can arrive later
+    let mut busy = HashSet::new();
+    let mut network = ChaosNetwork::new();
+
+    for _ in 0..CHAOS_STEPS {
+        let p = rand.gen_range(0.0..1.0);
+        match p {
+            p if p < 0.7 => {
+                // client operation
+                let clients: Vec<&String> = nodes.iter().filter(|n| !busy.contains(*n)).collect();
+                if clients.is_empty() {
+                    continue;
+                }
+                let node = clients.choose(&mut rand).unwrap().to_string();
+                let key = keys.choose(&mut rand).unwrap().clone();
+                let quorum = rand.gen_range(1..=config.replication_factor);
+                let op = match rand.gen_range(0.0..1.0) {
+                    p if p < 0.4 => ChaosOp::Get,
+                    p if p < 0.8 => ChaosOp::Put(random_string(8, &mut rand)),
+                    _ => ChaosOp::Delete,
+                };
+                let op_desc = match &op {
+                    ChaosOp::Get => format!("GET {}", key),
+                    ChaosOp::Put(value) => format!("PUT {}={}", key, value),
+                    ChaosOp::Delete => format!("DELETE {}", key),
+                };
+                let key_writes = writes.get_mut(&key).unwrap();
+                let mut expected = acceptable_values(
+                    key_writes,
+                    quorum,
+                    config.replication_factor,
+                    network.healthy_since,
+                );
+                let invoked = sys.time();
+                let result = chaos_request(&mut sys, &node, &key, &op, quorum)?;
+                schedule.push(format!(
+                    "{:.3} {} from {} with quorum {} -> {:?}",
+                    invoked, op_desc, node, quorum, result
+                ));
+
+                if result.is_none() {
+                    assume!(
+                        !network.is_healthy(),
+                        format!("Response is not returned by {} in healthy network", node)
+                    )?;
+                    busy.insert(node.clone());
+                }
+                // PUT can return the written value or a concurrently written one which wins according to LWW
+                if let ChaosOp::Put(value) = &op {
+                    expected.push(Some(value.clone()));
+                }
+                if let Some(returned) = &result {
+                    assume!(
+                        expected.contains(returned),
+                        format!(
+                            "Unexpected value {:?} for key {}, acceptable values: {:?}",
+                            returned, key, expected
+                        )
+                    )?;
+                }
+                let value = match op {
+                    ChaosOp::Get => continue,
+                    ChaosOp::Put(value) => Some(value),
+                    ChaosOp::Delete => None,
+                };
+                key_writes.push(ChaosWrite {
+                    value,
+                    quorum,
+                    invoked,
+                    completed: result.is_some(),
+                });
+            }
+            p if p < 0.8 => {
+                // disconnect node
+                let connected: Vec<&String> = nodes
+                    .iter()
+                    .filter(|n| !network.disconnected.contains(*n))
+                    .collect();
+                if connected.is_empty() {
+                    continue;
+                }
+                let node = connected.choose(&mut rand).unwrap().to_string();
+                sys.disconnect_node(&node);
+                schedule.push(format!("{:.3} disconnect {}", sys.time(), node));
+                network.disconnected.push(node);
+                network.healthy_since = f64::INFINITY;
+            }
+            p if p < 0.85 => {
+                // partition network into two random parts
+                let mut shuffled = nodes.clone();
+                shuffled.shuffle(&mut rand);
+                let split = rand.gen_range(1..shuffled.len());
+                let part1: Vec<&str> = shuffled[..split].iter().map(|s| &**s).collect();
+                let part2: Vec<&str> = shuffled[split..].iter().map(|s| &**s).collect();
+                sys.make_partition(&part1, &part2);
+                schedule.push(format!(
+                    "{:.3} partition {:?} {:?}",
+                    sys.time(),
+                    part1,
+                    part2
+                ));
+                network.partitioned = true;
+                network.healthy_since = f64::INFINITY;
+            }
+            p if p < 0.9 => {
+                // drop messages
+                let drop_rate = rand.gen_range(0.05..0.2);
+                sys.set_drop_rate(drop_rate);
+                schedule.push(format!("{:.3} drop rate {:.3}", sys.time(), drop_rate));
+                network.drop_rate = drop_rate;
+                network.healthy_since = f64::INFINITY;
+            }
+            _ => {
+                network.heal(&mut sys);
+                schedule.push(format!("{:.3} heal network", sys.time()));
+            }
+        }
+    }
+
+    // heal network and check that all replicas converged to the last written values
+    network.heal(&mut sys);
+    schedule.push(format!("{:.3} heal network", sys.time()));
+    // discard late responses to not completed requests and prefer other nodes as clients
+    for node in busy.iter() {
+        sys.check_mailbox(node);
+    }
+    let mut clients: Vec<&String> = nodes.iter().filter(|n| !busy.contains(*n)).collect();
+    if clients.is_empty() {
+        clients = nodes.iter().collect();
+    }
+    for key in keys.iter() {
+        let node = *clients.choose(&mut rand).unwrap();
+        let quorum = config.replication_factor;
+        let expected =
+            acceptable_values(writes.get(key).unwrap(), quorum, quorum, f64::NEG_INFINITY);
+        let result = chaos_request(&mut sys, node, key, &ChaosOp::Get, quorum)?;
+        schedule.push(format!(
+            "{:.3} GET {} from {} with quorum {} -> {:?}",
+            sys.time(),
+            key,
+            node,
+            quorum,
+            result
+        ));
+        assume!(
+            result.is_some(),
+            format!("GET_RESP is not returned by {} in healthy network", node)
+        )?;
+        let returned = result.unwrap();
+        assume!(
+            expected.contains(&returned),
+            format!(
+                "Replicas are not converged: value {:?} for key {}, acceptable values: {:?}",
+                returned, key, expected
+            )
+        )?;
+    }
+    Ok(true)
+}
+
+// Drifting local clocks for LWW tests: clock of each node runs faster or slower than the simulation time
+// with a constant rate and occasionally jumps, so timestamps of sequential writes coordinated by different nodes
+// can go backwards. Skews are updated while stepping the system through the methods below,
+// including the time spent waiting for responses.
+struct ClockDrift {
+    skews: HashMap<String, f64>,
+    rates: HashMap<String, f64>,
+    updated: f64,
+    rand: Pcg64,
+}
+
+const CLOCK_UPDATE_INTERVAL: f64 = 0.01;
+const CLOCK_MAX_SKEW: f64 = 1.;
+const CLOCK_JUMP_RATE: f64 = 0.1;
+const CLOCK_MAX_JUMP: f64 = 1.;
+const CLOCK_DRIFT_TIMEOUT: f64 = 10.;
+
+impl ClockDrift {
+    fn new(sys: &mut System<JsonMessage>, max_rate: f64, seed: u64) -> Self {
+        let mut rand = Pcg64::seed_from_u64(seed);
+        let mut skews = HashMap::new();
+        let mut rates = HashMap::new();
+        let mut nodes = sys.get_node_ids();
+        nodes.sort();
+        for node in nodes {
+            let skew = rand.gen_range(0.0..CLOCK_MAX_SKEW);
+            sys.set_clock_skew(&node, skew);
+            skews.insert(node.clone(), skew);
+            rates.insert(node, rand.gen_range(-max_rate..=max_rate));
+        }
+        Self {
+            skews,
+            rates,
+            updated: sys.time(),
+            rand,
         }
     }
-    non_replicas.append(&mut non_replicas_pre);
-    non_replicas
+
+    // Applies the drift accumulated since the last update
+    fn update(&mut self, sys: &mut System<JsonMessage>) {
+        let elapsed = sys.time() - self.updated;
+        self.updated = sys.time();
+        let mut nodes = self.skews.keys().cloned().collect::<Vec<_>>();
+        nodes.sort();
+        for node in nodes {
+            let mut skew = self.skews[&node] + self.rates[&node] * elapsed;
+            if self.rand.gen_range(0.0..1.0) < CLOCK_JUMP_RATE * elapsed {
+                skew += self.rand.gen_range(-CLOCK_MAX_JUMP..CLOCK_MAX_JUMP);
+            }
+            sys.set_clock_skew(&node, skew);
+            self.skews.insert(node, skew);
+        }
+    }
+
+    fn step(&mut self, sys: &mut System<JsonMessage>, duration: f64) {
+        let end_time = sys.time() + duration;
+        while sys.time() < end_time {
+            sys.step_for_duration(CLOCK_UPDATE_INTERVAL.min(end_time - sys.time()));
+            self.update(sys);
+        }
+    }
+
+    fn step_until_local_message(
+        &mut self,
+        sys: &mut System<JsonMessage>,
+        node: &str,
+    ) -> Option<Vec<JsonMessage>> {
+        let end_time = sys.time() + CLOCK_DRIFT_TIMEOUT;
+        while sys.time() < end_time {
+            let res = sys
+                .step_until_local_message_with_timeout(node, CLOCK_UPDATE_INTERVAL)
+                .ok();
+            self.update(sys);
+            if res.is_some() {
+                return res;
+            }
+        }
+        None
+    }
+}
+
+fn check_get_with_drift(
+    sys: &mut System<JsonMessage>,
+    clocks: &mut ClockDrift,
+    node: &str,
+    key: &str,
+    quorum: u8,
+    expected: Option<&str>,
+) -> TestResult {
+    sys.send_local(JsonMessage::from("GET", &GetMessage { key, quorum }), node);
+    let res = clocks.step_until_local_message(sys, node);
+    assume!(
+        res.is_some(),
+        format!("GET_RESP is not returned by {}", node)
+    )?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "GET_RESP")?;
+    let data: GetRespMessage = serde_json::from_str(&msg.data).unwrap();
+    assume_eq!(data.key, key)?;
+    check_quorum(sys, node, "GET", key)?;
+    assume_eq!(data.value, expected)?;
+    Ok(true)
+}
+
+fn check_put_with_drift(
+    sys: &mut System<JsonMessage>,
+    clocks: &mut ClockDrift,
+    node: &str,
+    key: &str,
+    value: &str,
+    quorum: u8,
+) -> TestResult {
+    sys.send_local(
+        JsonMessage::from("PUT", &PutMessage { key, value, quorum }),
+        node,
+    );
+    let res = clocks.step_until_local_message(sys, node);
+    assume!(
+        res.is_some(),
+        format!("PUT_RESP is not returned by {}", node)
+    )?;
+    let msgs = res.unwrap();
+    let msg = msgs.first().unwrap();
+    assume_eq!(msg.tip, "PUT_RESP")?;
+    let data: PutRespMessage = serde_json::from_str(&msg.data).unwrap();
+    assume_eq!(data.key, key)?;
+    check_quorum(sys, node, "PUT", key)?;
+    assume_eq!(data.value, value)?;
+    Ok(true)
+}
+
+const ANTI_ENTROPY_KEYS: usize = 3000;
+const ANTI_ENTROPY_DIFF: usize = 1500;
+const ANTI_ENTROPY_TRAFFIC_KEYS: usize = 1000;
+const ANTI_ENTROPY_TRAFFIC_DIFF: usize = 20;
+const ANTI_ENTROPY_TRAFFIC_SCALES: [usize; 3] = [1, 2, 4];
+const ANTI_ENTROPY_MAX_TRAFFIC_GROWTH: f64 = 2.;
+const ANTI_ENTROPY_VALUE_LENGTH: usize = 100;
+const ANTI_ENTROPY_PARTITION_DURATION: f64 = 60.;
+const ANTI_ENTROPY_SYNC_DURATION: f64 = 30.;
+
+struct SyncStats {
+    dataset_size: u64,
+    traffic: u64,
+    message_count: u64,
+}
+
+// Writes keys_count keys to all their replicas, then partitions the network into two halves for a long time
+// and updates diff_count keys having replicas on both sides with quorum 1, so that the replicas diverge.
+// After healing the network, steps for ANTI_ENTROPY_SYNC_DURATION without client requests,
+// checks that all replicas of updated keys received the new values and returns the network usage during this time.
+fn run_anti_entropy(
+    config: &TestConfig,
+    keys_count: usize,
+    diff_count: usize,
+) -> Result<SyncStats, String> {
+    let mut sys = build_system(config);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+    let nodes = sys.get_node_ids();
+
+    // put keys with quorum N
+    let mut keys = Vec::new();
+    let mut dataset_size = 0;
+    for _ in 0..keys_count {
+        let key = random_string(8, &mut rand).to_uppercase();
+        let value = random_string(ANTI_ENTROPY_VALUE_LENGTH, &mut rand);
+        let replicas = key_replicas(&key, config);
+        check_put(
+            &mut sys,
+            &replicas[0],
+            &key,
+            &value,
+            config.replication_factor,
+            100,
+        )?;
+        dataset_size += (key.len() + value.len()) as u64;
+        keys.push(key);
+    }
+
+    // partition the network into halves
+    let (part1, part2) = nodes.split_at(nodes.len() / 2);
+    let part1: Vec<&str> = part1.iter().map(|s| &**s).collect();
+    let part2: Vec<&str> = part2.iter().map(|s| &**s).collect();
+    sys.make_partition(&part1, &part2);
+
+    // update keys replicated on both sides from a random isolated replica with quorum 1,
+    // so that the other replicas and sloppy replicas do not receive the update
+    let mut updates = Vec::new();
+    for key in keys.iter() {
+        if updates.len() == diff_count {
+            break;
+        }
+        let replicas = key_replicas(key, config);
+        let on_both_sides = replicas.iter().any(|r| part1.contains(&r.as_str()))
+            && replicas.iter().any(|r| part2.contains(&r.as_str()));
+        if on_both_sides {
+            let replica = replicas.choose(&mut rand).unwrap();
+            let value = random_string(ANTI_ENTROPY_VALUE_LENGTH, &mut rand);
+            sys.disconnect_node(replica);
+            check_put(&mut sys, replica, key, &value, 1, 100)?;
+            sys.connect_node(replica);
+            updates.push((key.clone(), value));
+        }
+    }
+    assume_eq!(
+        updates.len(),
+        diff_count,
+        "Not enough keys with replicas on both sides of partition"
+    )?;
+    sys.step_for_duration(ANTI_ENTROPY_PARTITION_DURATION);
+
+    // heal the network and let the replicas synchronize without client requests
+    sys.reset_network();
+    let traffic = sys.get_network_traffic();
+    let message_count = sys.get_network_message_count();
+    sys.step_for_duration(ANTI_ENTROPY_SYNC_DURATION);
+    let stats = SyncStats {
+        dataset_size,
+        traffic: sys.get_network_traffic() - traffic,
+        message_count: sys.get_network_message_count() - message_count,
+    };
+
+    // check that each replica has the updated values by reading them from the isolated replica
+    for node in nodes.iter() {
+        sys.disconnect_node(node);
+        for (key, value) in updates.iter() {
+            if key_replicas(key, config).contains(node) {
+                check_get(&mut sys, node, key, 1, Some(value), 100)?;
+            }
+        }
+        sys.connect_node(node);
+    }
+    Ok(stats)
 }
 
 // TESTS ---------------------------------------------------------------------------------------------------------------
@@ -243,39 +836,89 @@ fn test_basic(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let last_replica = replicas.last().unwrap();
+    let non_replicas = key_non_replicas(&key, config);
     println!("Key {} replicas: {:?}", key, replicas);
     println!("Key {} non-replicas: {:?}", key, non_replicas);
 
     // get key from the first node
-    check_get(&mut sys, &nodes[0], &key, 2, None, 100)?;
+    check_get(&mut sys, &nodes[0], &key, config.read_quorum, None, 100)?;
 
     // put key from the first replica
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value, 2, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        config.write_quorum,
+        100,
+    )?;
 
     // get key from the last replica
-    check_get(&mut sys, &replicas[2], &key, 2, Some(&value), 100)?;
+    check_get(
+        &mut sys,
+        last_replica,
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )?;
 
     // get key from the first non-replica
-    check_get(&mut sys, &non_replicas[0], &key, 2, Some(&value), 100)?;
+    check_get(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )?;
 
     // update key from the last non-replica
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &non_replicas[2], &key, &value2, 2, 100)?;
+    check_put(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        &value2,
+        config.write_quorum,
+        100,
+    )?;
 
     // get key from the first node
-    check_get(&mut sys, &nodes[0], &key, 2, Some(&value2), 100)?;
+    check_get(
+        &mut sys,
+        &nodes[0],
+        &key,
+        config.read_quorum,
+        Some(&value2),
+        100,
+    )?;
 
     // delete key from the second non-replica
-    check_delete(&mut sys, &non_replicas[1], &key, 2, Some(&value2), 100)?;
+    check_delete(
+        &mut sys,
+        &non_replicas[1],
+        &key,
+        config.write_quorum,
+        Some(&value2),
+        100,
+    )?;
 
     // get key from the last replica
-    check_get(&mut sys, &replicas[2], &key, 2, None, 100)?;
+    check_get(&mut sys, last_replica, &key, config.read_quorum, None, 100)?;
 
     // get key from the first non-replica
-    check_get(&mut sys, &non_replicas[0], &key, 2, None, 100)
+    check_get(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        config.read_quorum,
+        None,
+        100,
+    )
 }
 
 fn test_replicas_check(config: &TestConfig) -> TestResult {
@@ -283,11 +926,18 @@ fn test_replicas_check(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand);
-    let replicas = key_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
 
-    // put key from the first replica with quorum 3
+    // put key from the first replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect each replica and check the stored value
     for replica in replicas.iter() {
@@ -302,23 +952,42 @@ fn test_concurrent_writes(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
 
     // concurrently put different values from the first and second non-replicas
     let value = random_string(8, &mut rand);
-    send_put(&mut sys, &non_replicas[0], &key, &value, 2);
+    send_put(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        &value,
+        config.write_quorum,
+    );
     // small delay to ensure writes will have different times
     sys.step_for_duration(0.01);
     let value2 = random_string(8, &mut rand);
-    send_put(&mut sys, &non_replicas[1], &key, &value2, 2);
+    send_put(
+        &mut sys,
+        &non_replicas[1],
+        &key,
+        &value2,
+        config.write_quorum,
+    );
 
     // the won value is the one written later
     // but it was not observed by the put from the first replica!
     check_put_result(&mut sys, &non_replicas[0], &key, &value, 100)?;
     check_put_result(&mut sys, &non_replicas[1], &key, &value2, 100)?;
 
-    // get key from the third non-replica with quorum 3
-    check_get(&mut sys, &non_replicas[2], &key, 3, Some(&value2), 100)
+    // get key from the third non-replica with quorum N
+    check_get(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        config.replication_factor,
+        Some(&value2),
+        100,
+    )
 }
 
 fn test_concurrent_writes_tie(config: &TestConfig) -> TestResult {
@@ -326,13 +995,25 @@ fn test_concurrent_writes_tie(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
 
     // concurrently put different values from the first and second non-replicas
     let value = random_string(8, &mut rand);
-    send_put(&mut sys, &non_replicas[0], &key, &value, 2);
+    send_put(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        &value,
+        config.write_quorum,
+    );
     let value2 = random_string(8, &mut rand);
-    send_put(&mut sys, &non_replicas[1], &key, &value2, 2);
+    send_put(
+        &mut sys,
+        &non_replicas[1],
+        &key,
+        &value2,
+        config.write_quorum,
+    );
 
     // with default seed, the won value is from the second replica
     // and is observed by the put from the first replica!
@@ -340,8 +1021,15 @@ fn test_concurrent_writes_tie(config: &TestConfig) -> TestResult {
     check_put_result(&mut sys, &non_replicas[0], &key, &won_value, 100)?;
     check_put_result(&mut sys, &non_replicas[1], &key, &won_value, 100)?;
 
-    // get key from the third non-replica with quorum 3
-    check_get(&mut sys, &non_replicas[2], &key, 3, Some(&won_value), 100)
+    // get key from the third non-replica with quorum N
+    check_get(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        config.replication_factor,
+        Some(won_value),
+        100,
+    )
 }
 
 fn test_stale_replica(config: &TestConfig) -> TestResult {
@@ -349,32 +1037,54 @@ fn test_stale_replica(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let last_replica = replicas.last().unwrap();
 
-    // put key from the first replica with quorum 3
+    // put key from the first replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect the last replica
-    sys.disconnect_node(&replicas[2]);
+    sys.disconnect_node(last_replica);
 
-    // update key from the first replica with quorum 2
+    // update key from the first replica with quorum W
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value2, 2, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value2,
+        config.write_quorum,
+        100,
+    )?;
 
     // disconnect the first replica
     sys.disconnect_node(&replicas[0]);
     // connect the last replica
-    sys.connect_node(&replicas[2]);
+    sys.connect_node(last_replica);
 
-    // read key from the second replica with quorum 2
+    // read key from the second replica with quorum R
     // should update the last replica via read repair or anti-entropy
-    check_get(&mut sys, &replicas[1], &key, 2, Some(&value2), 100)?;
+    check_get(
+        &mut sys,
+        &replicas[1],
+        &key,
+        config.read_quorum,
+        Some(&value2),
+        100,
+    )?;
 
     // step for a while and check whether the last replica got the recent value
     sys.steps(100);
-    sys.disconnect_node(&replicas[2]);
-    check_get(&mut sys, &replicas[2], &key, 1, Some(&value2), 100)
+    sys.disconnect_node(last_replica);
+    check_get(&mut sys, last_replica, &key, 1, Some(&value2), 100)
 }
 
 fn test_stale_replica_delete(config: &TestConfig) -> TestResult {
@@ -382,32 +1092,54 @@ fn test_stale_replica_delete(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let last_replica = replicas.last().unwrap();
 
-    // put key from the first replica with quorum 3
+    // put key from the first replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect the last replica
-    sys.disconnect_node(&replicas[2]);
+    sys.disconnect_node(last_replica);
 
-    // update key from the first replica with quorum 2
+    // update key from the first replica with quorum W
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value2, 2, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value2,
+        config.write_quorum,
+        100,
+    )?;
 
     // disconnect the first replica
     sys.disconnect_node(&replicas[0]);
     // connect the last replica
-    sys.connect_node(&replicas[2]);
+    sys.connect_node(last_replica);
 
     // delete key from the last replica (should return the last-written value)
-    check_delete(&mut sys, &replicas[2], &key, 2, Some(&value2), 100)?;
+    check_delete(
+        &mut sys,
+        last_replica,
+        &key,
+        config.write_quorum,
+        Some(&value2),
+        100,
+    )?;
 
     // connect the first replica
     sys.connect_node(&replicas[0]);
 
     // get key from the first replica (should return None)
-    check_get(&mut sys, &replicas[0], &key, 2, None, 100)
+    check_get(&mut sys, &replicas[0], &key, config.read_quorum, None, 100)
 }
 
 fn test_diverged_replicas(config: &TestConfig) -> TestResult {
@@ -415,12 +1147,19 @@ fn test_diverged_replicas(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
-    // put key from the first replica with quorum 3
+    // put key from the first replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect each replica and update key from it with quorum 1
     let mut new_values = Vec::new();
@@ -433,18 +1172,32 @@ fn test_diverged_replicas(config: &TestConfig) -> TestResult {
         // (make sure that the isolated replica is not among this key's replicas)
         loop {
             let some_key = random_string(8, &mut rand).to_uppercase();
-            if !key_replicas(&some_key, &sys).contains(&replica) {
-                check_get(&mut sys, &non_replicas[0], &some_key, 3, None, 100)?;
+            if !key_replicas(&some_key, config).contains(replica) {
+                check_get(
+                    &mut sys,
+                    &non_replicas[0],
+                    &some_key,
+                    config.replication_factor,
+                    None,
+                    100,
+                )?;
                 break;
             }
         }
         sys.connect_node(replica);
     }
 
-    // read key from the first replica with quorum 3
+    // read key from the first replica with quorum N
     // (the last written value should win)
     let expected = new_values.last().unwrap();
-    check_get(&mut sys, &replicas[0], &key, 3, Some(&expected), 100)
+    check_get(
+        &mut sys,
+        &replicas[0],
+        &key,
+        config.replication_factor,
+        Some(expected),
+        100,
+    )
 }
 
 fn test_sloppy_quorum_read(config: &TestConfig) -> TestResult {
@@ -452,23 +1205,37 @@ fn test_sloppy_quorum_read(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
     // disconnect the first replica
     sys.disconnect_node(&replicas[0]);
 
-    // put key from the second replica with quorum 2
+    // put key from the second replica with quorum W
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[1], &key, &value, 2, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[1],
+        &key,
+        &value,
+        config.write_quorum,
+        100,
+    )?;
 
     // disconnect the second replica
     sys.disconnect_node(&replicas[1]);
 
-    // read key from the last non-replica with quorum 2 (should use sloppy quorum)
+    // read key from the last non-replica with quorum R (should use sloppy quorum)
     // since non-replicas do not store any value, the last replica's value should win
     // the reading node could also do read repair on non-replicas to fix them
-    check_get(&mut sys, &non_replicas[2], &key, 2, Some(&value), 100)
+    check_get(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )
 }
 
 fn test_sloppy_quorum_write(config: &TestConfig) -> TestResult {
@@ -477,21 +1244,43 @@ fn test_sloppy_quorum_write(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let last_replica = replicas.last().unwrap();
 
-    // put key from the first node with quorum 3
+    // put key from the first node with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &nodes[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &nodes[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // temporarily disconnect the first replica
     sys.disconnect_node(&replicas[0]);
 
-    // update key from the second replica with quorum 3 (should use sloppy quorum)
+    // update key from the second replica with quorum N (should use sloppy quorum)
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[1], &key, &value2, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[1],
+        &key,
+        &value2,
+        config.replication_factor,
+        100,
+    )?;
 
-    // read key from the last replica with quorum 3 (should use sloppy quorum)
-    check_get(&mut sys, &replicas[2], &key, 3, Some(&value2), 100)?;
+    // read key from the last replica with quorum N (should use sloppy quorum)
+    check_get(
+        &mut sys,
+        last_replica,
+        &key,
+        config.replication_factor,
+        Some(&value2),
+        100,
+    )?;
 
     // reconnect the first replica and let it receive the update
     sys.connect_node(&replicas[0]);
@@ -508,23 +1297,38 @@ fn test_sloppy_quorum_tricky(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
-    // put key from the first node with quorum 3
+    // put key from the first node with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &nodes[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &nodes[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // temporarily disconnect the first replica
     sys.disconnect_node(&replicas[0]);
 
-    // update key from the second replica with quorum 3 (should use sloppy quorum)
+    // update key from the second replica with quorum N (should use sloppy quorum)
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[1], &key, &value2, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[1],
+        &key,
+        &value2,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect all members of the previous sloppy quorum
-    sys.disconnect_node(&replicas[1]);
-    sys.disconnect_node(&replicas[2]);
+    for replica in replicas[1..].iter() {
+        sys.disconnect_node(replica);
+    }
     sys.disconnect_node(&non_replicas[0]);
     // reconnect the first replica
     sys.connect_node(&replicas[0]);
@@ -533,16 +1337,30 @@ fn test_sloppy_quorum_tricky(config: &TestConfig) -> TestResult {
     // - second replica: value (outdated)
     // all connected non-replicas do not store the key
 
-    // read key from the last non-replica with quorum 2
+    // read key from the last non-replica with quorum R
     // (will receive old value only from the first replica and probably read repair it)
-    check_get(&mut sys, &non_replicas[2], &key, 2, Some(&value), 100)?;
+    check_get(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )?;
 
     // reconnect the second replica
     sys.connect_node(&replicas[1]);
 
-    // read key from the last non-replica with quorum 2
+    // read key from the last non-replica with quorum R
     // (should try to contact the main replicas first and receive the new value)
-    check_get(&mut sys, &non_replicas[2], &key, 2, Some(&value2), 100)
+    check_get(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        config.read_quorum,
+        Some(&value2),
+        100,
+    )
 }
 
 fn test_partition_clients(config: &TestConfig) -> TestResult {
@@ -550,8 +1368,8 @@ fn test_partition_clients(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
     // partition clients from all replicas
     let client1 = &non_replicas[0];
@@ -560,12 +1378,19 @@ fn test_partition_clients(config: &TestConfig) -> TestResult {
     let part2: Vec<&str> = non_replicas.iter().map(|s| &**s).collect();
     sys.make_partition(&part1, &part2);
 
-    // put key from client1 with quorum 2 (should use sloppy quorum without any normal replica)
+    // put key from client1 with quorum W (should use sloppy quorum without any normal replica)
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, client1, &key, &value, 2, 100)?;
-
-    // read key from client2 with quorum 2 (should use sloppy quorum without any normal replica)
-    check_get(&mut sys, client2, &key, 2, Some(&value), 100)
+    check_put(&mut sys, client1, &key, &value, config.write_quorum, 100)?;
+
+    // read key from client2 with quorum R (should use sloppy quorum without any normal replica)
+    check_get(
+        &mut sys,
+        client2,
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )
 }
 
 fn test_partition_mixed(config: &TestConfig) -> TestResult {
@@ -574,47 +1399,106 @@ fn test_partition_mixed(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let replica1 = &replicas[0];
-    let replica2 = &replicas[1];
-    let replica3 = &replicas[2];
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
     let client1 = &non_replicas[0];
     let client2 = &non_replicas[1];
     let client3 = &non_replicas[2];
 
-    // put key from the first node with quorum 3
+    // put key from the first node with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &nodes[0], &key, &value, 3, 100)?;
+    check_put(
+        &mut sys,
+        &nodes[0],
+        &key,
+        &value,
+        config.replication_factor,
+        100,
+    )?;
 
     // partition clients and replicas
-    let part1: Vec<&str> = vec![client1, client2, replica1];
-    let part2: Vec<&str> = vec![client3, replica2, replica3];
+    let (part1, part2) = mixed_partition(&replicas, &non_replicas);
     sys.make_partition(&part1, &part2);
 
     // partition 1
-    check_get(&mut sys, client1, &key, 2, Some(&value), 100)?;
+    check_get(
+        &mut sys,
+        client1,
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )?;
     let mut value2 = format!("{}-1", value);
-    check_put(&mut sys, client1, &key, &value2, 2, 100)?;
-    check_get(&mut sys, client2, &key, 2, Some(&value2), 100)?;
+    check_put(&mut sys, client1, &key, &value2, config.write_quorum, 100)?;
+    check_get(
+        &mut sys,
+        client2,
+        &key,
+        config.read_quorum,
+        Some(&value2),
+        100,
+    )?;
     value2 = format!("{}-2", value2);
-    check_put(&mut sys, client2, &key, &value2, 2, 100)?;
-    check_get(&mut sys, client2, &key, 2, Some(&value2), 100)?;
+    check_put(&mut sys, client2, &key, &value2, config.write_quorum, 100)?;
+    check_get(
+        &mut sys,
+        client2,
+        &key,
+        config.read_quorum,
+        Some(&value2),
+        100,
+    )?;
 
     // partition 2
-    check_get(&mut sys, client3, &key, 2, Some(&value), 100)?;
+    check_get(
+        &mut sys,
+        client3,
+        &key,
+        config.read_quorum,
+        Some(&value),
+        100,
+    )?;
     let value3 = format!("{}-3", value);
-    check_put(&mut sys, client3, &key, &value3, 2, 100)?;
-    check_get(&mut sys, client3, &key, 2, Some(&value3), 100)?;
+    check_put(&mut sys, client3, &key, &value3, config.write_quorum, 100)?;
+    check_get(
+        &mut sys,
+        client3,
+        &key,
+        config.read_quorum,
+        Some(&value3),
+        100,
+    )?;
 
     // heal partition
     sys.reset_network();
     sys.steps(100);
 
     // read key from all clients (should return the last-written value)
-    check_get(&mut sys, client1, &key, 2, Some(&value3), 100)?;
-    check_get(&mut sys, client2, &key, 2, Some(&value3), 100)?;
-    check_get(&mut sys, client3, &key, 2, Some(&value3), 100)?;
+    check_get(
+        &mut sys,
+        client1,
+        &key,
+        config.read_quorum,
+        Some(&value3),
+        100,
+    )?;
+    check_get(
+        &mut sys,
+        client2,
+        &key,
+        config.read_quorum,
+        Some(&value3),
+        100,
+    )?;
+    check_get(
+        &mut sys,
+        client3,
+        &key,
+        config.read_quorum,
+        Some(&value3),
+        100,
+    )?;
 
     // check all replicas (should return the last-written value)
     for replica in replicas.iter() {
@@ -624,6 +1508,172 @@ fn test_partition_mixed(config: &TestConfig) -> TestResult {
     Ok(true)
 }
 
+fn test_clock_drift_writes(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config);
+    let mut clocks = ClockDrift::new(&mut sys, config.clock_drift, config.seed);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    let key = random_string(8, &mut rand).to_uppercase();
+    let replicas = key_replicas(&key, config);
+
+    // sequentially update key from random replicas with quorum N while their clocks drift and jump
+    // (the coordinator has seen the previous write, so the new write should win even if its clock is behind)
+    let mut value = String::new();
+    for _ in 0..20 {
+        let replica = replicas.choose(&mut rand).unwrap();
+        value = random_string(8, &mut rand);
+        check_put_with_drift(
+            &mut sys,
+            &mut clocks,
+            replica,
+            &key,
+            &value,
+            config.replication_factor,
+        )?;
+        let pause = rand.gen_range(0.0..2.0);
+        clocks.step(&mut sys, pause);
+    }
+
+    // read key from all nodes with quorum R
+    for node in sys.get_node_ids() {
+        check_get_with_drift(
+            &mut sys,
+            &mut clocks,
+            &node,
+            &key,
+            config.read_quorum,
+            Some(&value),
+        )?;
+    }
+    Ok(true)
+}
+
+fn test_clock_drift_stale_replica(config: &TestConfig) -> TestResult {
+    let mut sys = build_system(config);
+    let mut clocks = ClockDrift::new(&mut sys, config.clock_drift, config.seed);
+    let mut rand = Pcg64::seed_from_u64(config.seed);
+
+    let key = random_string(8, &mut rand).to_uppercase();
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
+
+    // put key from the first non-replica with quorum N
+    let value = random_string(8, &mut rand);
+    check_put_with_drift(
+        &mut sys,
+        &mut clocks,
+        &non_replicas[0],
+        &key,
+        &value,
+        config.replication_factor,
+    )?;
+    clocks.step(&mut sys, 10.);
+
+    // disconnect the first replica
+    sys.disconnect_node(&replicas[0]);
+
+    // update key several times from the other replicas with quorum N - 1 (all connected replicas)
+    let mut expected = value;
+    for i in 0..5 {
+        let replica = &replicas[1 + i % (replicas.len() - 1)];
+        let value2 = random_string(8, &mut rand);
+        check_put_with_drift(
+            &mut sys,
+            &mut clocks,
+            replica,
+            &key,
+            &value2,
+            config.replication_factor - 1,
+        )?;
+        expected = value2;
+        clocks.step(&mut sys, 10.);
+    }
+
+    // connect the first replica and read key from it with quorum N
+    // (it stores the initial value, which should lose to the updates
+    // even if their coordinators' clocks are behind the clock of the initial write coordinator)
+    sys.connect_node(&replicas[0]);
+    check_get_with_drift(
+        &mut sys,
+        &mut clocks,
+        &replicas[0],
+        &key,
+        config.replication_factor,
+        Some(&expected),
+    )?;
+
+    // step for a while and check whether the first replica got the recent value
+    clocks.step(&mut sys, 10.);
+    sys.disconnect_node(&replicas[0]);
+    check_get_with_drift(
+        &mut sys,
+        &mut clocks,
+        &replicas[0],
+        &key,
+        1,
+        Some(&expected),
+    )
+}
+
+fn test_chaos_monkey(config: &TestConfig) -> TestResult {
+    let mut schedule = Vec::new();
+    let res = run_chaos_monkey(config, &mut schedule);
+    if res.is_err() {
+        println!(
+            "\nChaos monkey failed with seed {} (run with --seed {} --monkeys 1 to reproduce)",
+            config.seed, config.seed
+        );
+        println!("Schedule:");
+        for action in schedule.iter() {
+            println!("  {}", action);
+        }
+    }
+    res
+}
+
+fn test_anti_entropy(config: &TestConfig) -> TestResult {
+    let stats = run_anti_entropy(config, ANTI_ENTROPY_KEYS, ANTI_ENTROPY_DIFF)?;
+    println!(
+        "\nSync cost: traffic {}, messages {}",
+        stats.traffic, stats.message_count
+    );
+    Ok(true)
+}
+
+fn test_anti_entropy_traffic(config: &TestConfig) -> TestResult {
+    // the difference between replicas is fixed while the dataset grows
+    let mut results = Vec::new();
+    println!(
+        "\nSync cost for {} updated keys:",
+        ANTI_ENTROPY_TRAFFIC_DIFF
+    );
+    for scale in ANTI_ENTROPY_TRAFFIC_SCALES {
+        let keys_count = ANTI_ENTROPY_TRAFFIC_KEYS * scale;
+        let stats = run_anti_entropy(config, keys_count, ANTI_ENTROPY_TRAFFIC_DIFF)?;
+        println!(
+            "  - {} keys: traffic {} (dataset size {}), messages {}",
+            keys_count, stats.traffic, stats.dataset_size, stats.message_count
+        );
+        assume!(
+            stats.traffic < stats.dataset_size,
+            format!(
+                "Sync traffic {} is not less than the whole dataset size {}",
+                stats.traffic, stats.dataset_size
+            )
+        )?;
+        results.push(stats);
+    }
+    let growth = results.last().unwrap().traffic as f64 / results[0].traffic.max(1) as f64;
+    assume!(
+        growth <= ANTI_ENTROPY_MAX_TRAFFIC_GROWTH,
+        format!(
+            "Sync traffic grows {:.2} times with {} times larger dataset (should depend on the difference size)",
+            growth,
+            ANTI_ENTROPY_TRAFFIC_SCALES.last().unwrap() / ANTI_ENTROPY_TRAFFIC_SCALES[0]
+        )
+    )
+}
+
 // CLI -----------------------------------------------------------------------------------------------------------------
 
 /// Replicated KV Store Homework Tests
@@ -650,6 +1700,38 @@ struct Args {
     #[clap(long, short, default_value = "123")]
     seed: u64,
 
+    /// Replication factor (N)
+    #[clap(long, default_value = "3")]
+    replicas: u8,
+
+    /// Read quorum used in tests instead of 2 (R, majority of replicas by default)
+    #[clap(long)]
+    read_quorum: Option<u8>,
+
+    /// Write quorum used in tests instead of 2 (W, majority of replicas by default)
+    #[clap(long)]
+    write_quorum: Option<u8>,
+
+    /// Number of virtual nodes per node for ring placement (consecutive nodes are used if 0)
+    #[clap(long, default_value = "0")]
+    vnodes: u32,
+
+    /// Check that coordinators receive acknowledgements from quorum of nodes before responding
+    #[clap(long)]
+    check_quorums: bool,
+
+    /// Run LWW tests with clocks drifting with the given max rate (e.g. 0.1 for 10%)
+    #[clap(long)]
+    clock_drift: Option<f64>,
+
+    /// Run anti-entropy tests
+    #[clap(long)]
+    anti_entropy: bool,
+
+    /// Number of chaos monkey runs
+    #[clap(long, short, default_value = "0")]
+    monkeys: u32,
+
     /// Path to dslib directory
     #[clap(long = "lib", short = 'l', default_value = "../../dslib")]
     dslib_path: String,
@@ -657,6 +1739,11 @@ struct Args {
 
 // MAIN ----------------------------------------------------------------------------------------------------------------
 
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}", msg);
+    std::process::exit(2);
+}
+
 fn main() {
     let args = Args::parse();
     if args.debug {
@@ -665,12 +1752,38 @@ fn main() {
     env::set_var("PYTHONPATH", format!("{}/python", args.dslib_path));
     env::set_var("PYTHONHASHSEED", args.seed.to_string());
     let node_factory = PyNodeFactory::new(&args.solution_path, "StorageNode");
+    let majority = args.replicas / 2 + 1;
     let config = TestConfig {
         node_factory: &node_factory,
         node_count: args.node_count,
         seed: args.seed,
+        replication_factor: args.replicas,
+        read_quorum: args.read_quorum.unwrap_or(majority),
+        write_quorum: args.write_quorum.unwrap_or(majority),
+        vnodes: args.vnodes,
+        check_quorums: args.check_quorums,
+        clock_drift: args.clock_drift.unwrap_or(0.),
     };
 
+    if config.replication_factor < 3 {
+        usage_error("tests require at least 3 replicas, use --replicas 3 or more");
+    }
+    if config.node_count < config.replication_factor as u32 + 3 {
+        usage_error(&format!(
+            "tests require at least 3 non-replicas, use -n {} or more with {} replicas",
+            config.replication_factor as u32 + 3,
+            config.replication_factor
+        ));
+    }
+    if !(config.read_quorum >= 1
+        && config.write_quorum >= 1
+        && config.read_quorum <= config.replication_factor
+        && config.write_quorum <= config.replication_factor
+        && config.read_quorum + config.write_quorum > config.replication_factor)
+    {
+        usage_error("tests require 1 <= R, W <= N and R + W > N");
+    }
+
     let mut tests = TestSuite::new();
     tests.add("BASIC", test_basic, config);
     tests.add("REPLICAS CHECK", test_replicas_check, config);
@@ -684,6 +1797,27 @@ fn main() {
     tests.add("SLOPPY QUORUM TRICKY", test_sloppy_quorum_tricky, config);
     tests.add("PARTITION CLIENTS", test_partition_clients, config);
     tests.add("PARTITION MIXED", test_partition_mixed, config);
+    if args.clock_drift.is_some() {
+        tests.add("CLOCK DRIFT WRITES", test_clock_drift_writes, config);
+        tests.add(
+            "CLOCK DRIFT STALE REPLICA",
+            test_clock_drift_stale_replica,
+            config,
+        );
+    }
+    if args.anti_entropy {
+        tests.add("ANTI-ENTROPY", test_anti_entropy, config);
+        tests.add("ANTI-ENTROPY TRAFFIC", test_anti_entropy_traffic, config);
+    }
+    for run in 0..args.monkeys {
+        let mut run_config = config;
+        run_config.seed = config.seed + run as u64;
+        tests.add(
+            &format!("CHAOS MONKEY (run {})", run + 1),
+            test_chaos_monkey,
+            run_config,
+        );
+    }
 
     if args.test.is_none() {
         tests.run();
@@ -0,0 +1,141 @@
+// Verification of quorums using the network trace of operation coordinators.
+//
+// dslib does not expose the full network trace, so each solution node is wrapped into QuorumTracer,
+// which passes all events to the node and records, for each local GET/PUT/DELETE request in progress,
+// the nodes that sent replies of type <OP>_ACK mentioning the request key to the coordinator and their times.
+// Such messages are considered acknowledgements from real or sloppy replicas.
+// When the response is returned, check_quorum() verifies that before sending the response the coordinator
+// heard from at least quorum distinct nodes. The coordinator itself is always counted,
+// since it can apply the operation locally.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+
+use dslib::node::{Context, LocalEventType, Node};
+use dslib::pynode::{JsonMessage, PyNode};
+use dslib::system::System;
+use dslib::test::TestResult;
+
+struct TracedOperation {
+    tip: String,
+    key: String,
+    quorum: usize,
+    // sender and receive time of each acknowledgement
+    acks: Vec<(String, f64)>,
+}
+
+thread_local! {
+    // operations in progress for each node, in order of their invocation
+    static OPERATIONS: RefCell<HashMap<String, Vec<TracedOperation>>> = RefCell::new(HashMap::new());
+}
+
+pub struct QuorumTracer {
+    node: PyNode,
+}
+
+impl QuorumTracer {
+    pub fn new(node: PyNode) -> Self {
+        Self { node }
+    }
+}
+
+impl Node<JsonMessage> for QuorumTracer {
+    fn id(&self) -> &String {
+        self.node.id()
+    }
+
+    fn on_message(&mut self, msg: JsonMessage, from: String, ctx: &mut Context<JsonMessage>) {
+        OPERATIONS.with(|operations| {
+            if let Some(ops) = operations.borrow_mut().get_mut(self.node.id()) {
+                for op in ops.iter_mut() {
+                    if msg.tip == format!("{}_ACK", op.tip) && mentions_key(&msg.data, &op.key) {
+                        op.acks.push((from.clone(), ctx.time()));
+                    }
+                }
+            }
+        });
+        self.node.on_message(msg, from, ctx);
+    }
+
+    fn on_local_message(&mut self, msg: JsonMessage, ctx: &mut Context<JsonMessage>) {
+        if matches!(msg.tip.as_str(), "GET" | "PUT" | "DELETE") {
+            let data: serde_json::Value = serde_json::from_str(&msg.data).unwrap();
+            let op = TracedOperation {
+                tip: msg.tip.clone(),
+                key: data["key"].as_str().unwrap().to_string(),
+                quorum: data["quorum"].as_u64().unwrap() as usize,
+                acks: Vec::new(),
+            };
+            OPERATIONS.with(|operations| {
+                operations
+                    .borrow_mut()
+                    .entry(self.node.id().clone())
+                    .or_default()
+                    .push(op)
+            });
+        }
+        self.node.on_local_message(msg, ctx);
+    }
+
+    fn on_timer(&mut self, timer: String, ctx: &mut Context<JsonMessage>) {
+        self.node.on_timer(timer, ctx);
+    }
+
+    fn max_size(&mut self) -> u64 {
+        self.node.max_size()
+    }
+}
+
+// Keys are compared as JSON strings to avoid matching keys which are substrings of other keys
+fn mentions_key(data: &str, key: &str) -> bool {
+    data.contains(&serde_json::to_string(key).unwrap())
+}
+
+// Forgets operations traced in the previous test
+pub fn reset() {
+    OPERATIONS.with(|operations| operations.borrow_mut().clear());
+}
+
+// Checks the earliest traced operation with the given type and key at the node, which has just returned the response.
+// Only acknowledgements received not later than the response was sent are counted.
+// Does nothing if the node is not traced.
+pub fn check_quorum(sys: &System<JsonMessage>, node: &str, tip: &str, key: &str) -> TestResult {
+    let op = OPERATIONS.with(|operations| {
+        let mut operations = operations.borrow_mut();
+        let ops = operations.get_mut(node)?;
+        let idx = ops.iter().position(|op| op.tip == tip && op.key == key)?;
+        Some(ops.remove(idx))
+    });
+    if let Some(op) = op {
+        let resp_tip = format!("{}_RESP", tip);
+        let resp_time = sys
+            .get_local_events(node)
+            .iter()
+            .rev()
+            .find(|e| {
+                matches!(e.tip, LocalEventType::LocalMessageSend)
+                    && e.msg.as_ref().is_some_and(|m| m.tip == resp_tip)
+            })
+            .map(|e| e.time)
+            .unwrap_or(f64::INFINITY);
+        let mut acks: BTreeSet<String> = op
+            .acks
+            .into_iter()
+            .filter(|(_, time)| *time <= resp_time)
+            .map(|(from, _)| from)
+            .collect();
+        acks.insert(node.to_string());
+        if acks.len() < op.quorum {
+            return Err(format!(
+                "quorum cheat: {} for key {} is returned by {} after hearing from {} of {} nodes {:?}",
+                resp_tip,
+                key,
+                node,
+                acks.len(),
+                op.quorum,
+                acks
+            ));
+        }
+    }
+    Ok(true)
+}
@@ -1,12 +1,15 @@
+import bisect
 import hashlib
 from dslib import Context, Message, Node
 from typing import List
 
 
 class StorageNode(Node):
-    def __init__(self, node_id: str, nodes: List[str]):
+    def __init__(self, node_id: str, nodes: List[str], replication_factor: int = 3, vnodes: int = 0):
         self._id = node_id
         self._nodes = nodes
+        self._replication_factor = replication_factor
+        self._vnodes = vnodes
         self._data = {}
 
     def on_local_message(self, msg: Message, ctx: Context):
@@ -14,7 +17,7 @@ class StorageNode(Node):
         # Request:
         #   GET {
         #       "key": "some key",
-        #       "quorum": 1-3
+        #       "quorum": 1-N
         #   }
         # Response:
         #   GET_RESP {
@@ -24,7 +27,7 @@ class StorageNode(Node):
         #   }
         if msg.type == 'GET':
             key = msg['key']
-            print("[py] Key", key, "replicas:", get_key_replicas(key, len(self._nodes)))
+            print("[py] Key", key, "replicas:", get_key_replicas(key, len(self._nodes), self._replication_factor, self._vnodes))
             if key in self._data:
                 values = [self._data.get(key)]
                 context = 'todo'
@@ -44,7 +47,7 @@ class StorageNode(Node):
         #       "key": "some key",
         #       "value: "some value",
         #       "context": context from previous read or write operation (can be null if key is written first time),
-        #       "quorum": 1-3
+        #       "quorum": 1-N
         #   }
         # Response:
         #   PUT_RESP {
@@ -72,15 +75,28 @@ class StorageNode(Node):
         pass
 
 
-def get_key_replicas(key: str, node_count: int):
-    replicas = []
-    key_hash = int.from_bytes(hashlib.md5(key.encode('utf8')).digest(), 'little', signed=False)
-    cur = key_hash % node_count
-    for _ in range(3):
-        replicas.append(cur)
-        cur = get_next_replica(cur, node_count)
-    return replicas
+def get_key_replicas(key: str, node_count: int, replication_factor: int = 3, vnodes: int = 0):
+    return get_key_preference_list(key, node_count, vnodes)[:replication_factor]
 
 
-def get_next_replica(i, node_count: int):
-    return (i + 1) % node_count
\ No newline at end of file
+# Returns indices of all nodes in order of preference for storing the key:
+# the first replication_factor nodes are key replicas, the rest can be used in sloppy quorum.
+# Without virtual nodes, replicas are consecutive node indices starting from the key hash,
+# otherwise nodes are ordered by walking the ring with vnodes points per node clockwise from the key hash.
+def get_key_preference_list(key: str, node_count: int, vnodes: int = 0):
+    key_hash = get_hash(key)
+    if vnodes == 0:
+        start = key_hash % node_count
+        return [(start + i) % node_count for i in range(node_count)]
+    ring = sorted((get_hash('{}-{}'.format(node, vnode)), node) for node in range(node_count) for vnode in range(vnodes))
+    start = bisect.bisect_left(ring, (key_hash, -1))
+    nodes = []
+    for i in range(len(ring)):
+        node = ring[(start + i) % len(ring)][1]
+        if node not in nodes:
+            nodes.append(node)
+    return nodes
+
+
+def get_hash(s: str):
+    return int.from_bytes(hashlib.md5(s.encode('utf8')).digest(), 'little', signed=False)
@@ -56,6 +56,10 @@ struct TestConfig<'a> {
     node_factory: &'a PyNodeFactory,
     node_count: u32,
     seed: u64,
+    replication_factor: u8,
+    read_quorum: u8,
+    write_quorum: u8,
+    vnodes: u32,
 }
 
 fn init_logger(level: LevelFilter) {
@@ -73,9 +77,24 @@ fn build_system(config: &TestConfig) -> System<JsonMessage> {
         node_ids.push(format!("{}", n));
     }
     for node_id in node_ids.iter() {
-        let node = config
-            .node_factory
-            .build(node_id, (node_id, node_ids.clone()), config.seed);
+        // replication parameters are passed only if they differ from the defaults
+        // to support solutions without their support
+        let node = if config.replication_factor == 3 && config.vnodes == 0 {
+            config
+                .node_factory
+                .build(node_id, (node_id, node_ids.clone()), config.seed)
+        } else {
+            config.node_factory.build(
+                node_id,
+                (
+                    node_id,
+                    node_ids.clone(),
+                    config.replication_factor,
+                    config.vnodes,
+                ),
+                config.seed,
+            )
+        };
         sys.add_node(rc!(refcell!(node)));
         let clock_skew = sys.gen_range(0.0..1.0);
         sys.set_clock_skew(node_id, clock_skew);
@@ -231,40 +250,66 @@ fn random_string(length: usize, rand: &mut Pcg64) -> String {
         .collect()
 }
 
-fn key_replicas(key: &str, sys: &System<JsonMessage>) -> Vec<String> {
-    let node_count = sys.node_count();
-    let mut replicas = Vec::new();
-    let hash = md5::compute(key);
-    let hash128 = LittleEndian::read_u128(&hash.0);
-    let mut replica = (hash128 % node_count as u128) as u32;
-    for _ in 0..3 {
-        replicas.push(replica.to_string());
-        replica += 1;
-        if replica == node_count {
-            replica = 0;
+// The same as get_key_replicas() from solution template
+fn placement_hash(s: &str) -> u128 {
+    let hash = md5::compute(s);
+    LittleEndian::read_u128(&hash.0)
+}
+
+// All nodes in order of preference for storing the key: the first replication_factor nodes are key replicas,
+// the rest are used as substitutes in sloppy quorum.
+// Without virtual nodes, replicas are consecutive node indices starting from the key hash,
+// otherwise nodes are ordered by walking the ring with vnodes points per node clockwise from the key hash.
+fn key_preference_list(key: &str, config: &TestConfig) -> Vec<String> {
+    let node_count = config.node_count;
+    let key_hash = placement_hash(key);
+    let mut nodes = Vec::new();
+    if config.vnodes == 0 {
+        let start = (key_hash % node_count as u128) as u32;
+        for i in 0..node_count {
+            nodes.push(((start + i) % node_count).to_string());
         }
-    }
-    replicas
-}
-
-fn key_non_replicas(key: &str, sys: &System<JsonMessage>) -> Vec<String> {
-    let replicas = key_replicas(&key, sys);
-    let mut non_replicas_pre = Vec::new();
-    let mut non_replicas = Vec::new();
-    let mut pre = true;
-    for node in sys.get_node_ids() {
-        if replicas.contains(&node) {
-            pre = false;
-            continue;
+    } else {
+        let mut ring = Vec::new();
+        for node in 0..node_count {
+            for vnode in 0..config.vnodes {
+                ring.push((placement_hash(&format!("{}-{}", node, vnode)), node));
+            }
         }
-        if pre {
-            non_replicas_pre.push(node);
-        } else {
-            non_replicas.push(node);
+        ring.sort();
+        let start = ring.partition_point(|(hash, _)| *hash < key_hash);
+        for i in 0..ring.len() {
+            let node = ring[(start + i) % ring.len()].1.to_string();
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
         }
     }
-    non_replicas.append(&mut non_replicas_pre);
-    non_replicas
+    nodes
+}
+
+fn key_replicas(key: &str, config: &TestConfig) -> Vec<String> {
+    let mut nodes = key_preference_list(key, config);
+    nodes.truncate(config.replication_factor as usize);
+    nodes
+}
+
+fn key_non_replicas(key: &str, config: &TestConfig) -> Vec<String> {
+    let nodes = key_preference_list(key, config);
+    nodes[config.replication_factor as usize..].to_vec()
+}
+
+// Partitions nodes into two parts: the first two non-replicas with the first replica (and remaining non-replicas),
+// and the third non-replica with the rest of replicas
+fn mixed_partition<'a>(
+    replicas: &'a [String],
+    non_replicas: &'a [String],
+) -> (Vec<&'a str>, Vec<&'a str>) {
+    let mut part1: Vec<&str> = vec![&non_replicas[0], &non_replicas[1], &replicas[0]];
+    part1.extend(non_replicas[3..].iter().map(|s| &**s));
+    let mut part2: Vec<&str> = vec![&non_replicas[2]];
+    part2.extend(replicas[1..].iter().map(|s| &**s));
+    (part1, part2)
 }
 
 // TESTS -------------------------------------------------------------------------------------------
@@ -275,34 +320,86 @@ fn test_basic(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let last_replica = replicas.last().unwrap();
+    let non_replicas = key_non_replicas(&key, config);
     println!("Key {} replicas: {:?}", key, replicas);
 
     // get key from the first node
-    check_get(&mut sys, &nodes[0], &key, 2, Some(vec![]), 100)?;
+    check_get(
+        &mut sys,
+        &nodes[0],
+        &key,
+        config.read_quorum,
+        Some(vec![]),
+        100,
+    )?;
 
     // put key from the first replica
     let value = random_string(8, &mut rand);
-    let (values, _) = check_put(&mut sys, &replicas[0], &key, &value, None, 2, 100)?;
+    let (values, _) = check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        None,
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
     assume_eq!(values[0], value)?;
 
     // get key from the last replica
-    check_get(&mut sys, &replicas[2], &key, 2, Some(vec![&value]), 100)?;
+    check_get(
+        &mut sys,
+        last_replica,
+        &key,
+        config.read_quorum,
+        Some(vec![&value]),
+        100,
+    )?;
 
     // get key from the first non-replica
-    check_get(&mut sys, &non_replicas[0], &key, 2, Some(vec![&value]), 100)?;
+    check_get(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        config.read_quorum,
+        Some(vec![&value]),
+        100,
+    )?;
 
     // update key from the last non-replica
-    let (_, ctx) = check_get(&mut sys, &non_replicas[2], &key, 2, Some(vec![&value]), 100)?;
+    let (_, ctx) = check_get(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        config.read_quorum,
+        Some(vec![&value]),
+        100,
+    )?;
     let value2 = random_string(8, &mut rand);
-    let (values, _) = check_put(&mut sys, &non_replicas[2], &key, &value2, ctx, 2, 100)?;
+    let (values, _) = check_put(
+        &mut sys,
+        &non_replicas[2],
+        &key,
+        &value2,
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
     assume_eq!(values[0], value2)?;
 
     // get key from the first node
-    check_get(&mut sys, &nodes[0], &key, 2, Some(vec![&value2]), 100)?;
+    check_get(
+        &mut sys,
+        &nodes[0],
+        &key,
+        config.read_quorum,
+        Some(vec![&value2]),
+        100,
+    )?;
     Ok(true)
 }
 
@@ -311,28 +408,59 @@ fn test_stale_replica(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let last_replica = replicas.last().unwrap();
+    let non_replicas = key_non_replicas(&key, config);
 
-    // put key from the first non-replica with quorum 3
+    // put key from the first non-replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &non_replicas[0], &key, &value, None, 3, 100)?;
+    check_put(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        &value,
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect the first replica
     sys.disconnect_node(&replicas[0]);
 
-    // update key from the last replica with quorum 2
-    let (_, ctx) = check_get(&mut sys, &replicas[2], &key, 2, Some(vec![&value]), 100)?;
+    // update key from the last replica with quorum W
+    let (_, ctx) = check_get(
+        &mut sys,
+        last_replica,
+        &key,
+        config.read_quorum,
+        Some(vec![&value]),
+        100,
+    )?;
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[2], &key, &value2, ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        last_replica,
+        &key,
+        &value2,
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
 
     // disconnect the last replica
-    sys.disconnect_node(&replicas[2]);
+    sys.disconnect_node(last_replica);
     // connect the first replica
     sys.connect_node(&replicas[0]);
 
-    // read key from the second replica with quorum 2
-    check_get(&mut sys, &replicas[1], &key, 2, Some(vec![&value2]), 100)?;
+    // read key from the second replica with quorum R
+    check_get(
+        &mut sys,
+        &replicas[1],
+        &key,
+        config.read_quorum,
+        Some(vec![&value2]),
+        100,
+    )?;
 
     // step for a while and check whether the first replica got the recent value
     sys.steps(100);
@@ -346,25 +474,48 @@ fn test_concurrent_writes_1(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
     let node1 = &non_replicas.get(0).unwrap();
     let node2 = &non_replicas.get(1).unwrap();
     let node3 = &non_replicas.get(2).unwrap();
 
-    // put key from node1 (quorum=2)
+    // put key from node1 (quorum=W)
     let value1 = random_string(8, &mut rand);
-    let (values, _) = check_put(&mut sys, node1, &key, &value1, None, 2, 100)?;
+    let (values, _) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &value1,
+        None,
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
     assume_eq!(values[0], value1)?;
 
-    // concurrently (using same context) put key from node2 (quorum=2)
+    // concurrently (using same context) put key from node2 (quorum=W)
     let value2 = random_string(8, &mut rand);
-    let (values, _) = check_put(&mut sys, node2, &key, &value2, None, 2, 100)?;
+    let (values, _) = check_put(
+        &mut sys,
+        node2,
+        &key,
+        &value2,
+        None,
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 2, "Expected two values")?;
 
-    // read key from node3 (quorum=2)
+    // read key from node3 (quorum=R)
     // should return both values for reconciliation by the client
-    check_get(&mut sys, node3, &key, 2, Some(vec![&value1, &value2]), 100)?;
+    check_get(
+        &mut sys,
+        node3,
+        &key,
+        config.read_quorum,
+        Some(vec![&value1, &value2]),
+        100,
+    )?;
     Ok(true)
 }
 
@@ -373,32 +524,54 @@ fn test_concurrent_writes_2(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
     let node1 = &non_replicas.get(0).unwrap();
     let node2 = &non_replicas.get(1).unwrap();
     let node3 = &non_replicas.get(2).unwrap();
 
-    // put key from node1 (quorum=2)
+    // put key from node1 (quorum=W)
     let value1 = random_string(8, &mut rand);
-    send_put(&mut sys, node1, &key, &value1, 2, None);
+    send_put(&mut sys, node1, &key, &value1, config.write_quorum, None);
 
-    // concurrently (using same context) put key from node2 (quorum=2)
+    // concurrently (using same context) put key from node2 (quorum=W)
     let value2 = random_string(8, &mut rand);
-    send_put(&mut sys, node2, &key, &value2, 2, None);
+    send_put(&mut sys, node2, &key, &value2, config.write_quorum, None);
 
     // wait until both puts are processed
     check_put_result(&mut sys, node1, &key, 100)?;
     check_put_result(&mut sys, node2, &key, 100)?;
 
-    // read key from node3 (quorum=2)
+    // read key from node3 (quorum=R)
     // should return both values for reconciliation by the client
-    let (_, ctx) = check_get(&mut sys, node3, &key, 2, Some(vec![&value1, &value2]), 100)?;
+    let (_, ctx) = check_get(
+        &mut sys,
+        node3,
+        &key,
+        config.read_quorum,
+        Some(vec![&value1, &value2]),
+        100,
+    )?;
     // put new reconciled value using the obtained context
     let value3 = [value1, value2].join("+");
-    check_put(&mut sys, node3, &key, &value3, ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        node3,
+        &key,
+        &value3,
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
 
-    // read key from node1 (quorum=2)
-    check_get(&mut sys, node1, &key, 2, Some(vec![&value3]), 100)?;
+    // read key from node1 (quorum=R)
+    check_get(
+        &mut sys,
+        node1,
+        &key,
+        config.read_quorum,
+        Some(vec![&value3]),
+        100,
+    )?;
     Ok(true)
 }
 
@@ -407,8 +580,8 @@ fn test_concurrent_writes_3(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
     // put key from the first replica (quorum=1)
     let value1 = random_string(8, &mut rand);
@@ -418,12 +591,12 @@ fn test_concurrent_writes_3(config: &TestConfig) -> TestResult {
     let value2 = random_string(8, &mut rand);
     check_put(&mut sys, &replicas[1], &key, &value2, None, 1, 100)?;
 
-    // read key from the first non-replica (quorum=3)
+    // read key from the first non-replica (quorum=N)
     check_get(
         &mut sys,
         &non_replicas[0],
         &key,
-        3,
+        config.replication_factor,
         Some(vec![&value1, &value2]),
         100,
     )?;
@@ -435,12 +608,20 @@ fn test_diverged_replicas(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
-    // put key from the first replica with quorum 3
+    // put key from the first replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &replicas[0], &key, &value, None, 3, 100)?;
+    check_put(
+        &mut sys,
+        &replicas[0],
+        &key,
+        &value,
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // disconnect each replica and put value from it
     let mut new_values = Vec::new();
@@ -456,12 +637,19 @@ fn test_diverged_replicas(config: &TestConfig) -> TestResult {
         // (make sure that the isolated replicas are not among this key's replicas)
         loop {
             let some_key = random_string(8, &mut rand).to_uppercase();
-            let some_key_replicas = key_replicas(&some_key, &sys);
+            let some_key_replicas = key_replicas(&some_key, config);
             if replicas
                 .iter()
                 .all(|node| !some_key_replicas.contains(node))
             {
-                check_get(&mut sys, &non_replicas[0], &some_key, 3, Some(vec![]), 100)?;
+                check_get(
+                    &mut sys,
+                    &non_replicas[0],
+                    &some_key,
+                    config.replication_factor,
+                    Some(vec![]),
+                    100,
+                )?;
                 break;
             }
         }
@@ -472,10 +660,17 @@ fn test_diverged_replicas(config: &TestConfig) -> TestResult {
         sys.connect_node(replica);
     }
 
-    // read key from the first replica with quorum 3
-    // should return all three conflicting values
+    // read key from the first replica with quorum N
+    // should return conflicting values from all replicas
     let expected = new_values.iter().map(String::as_str).collect();
-    check_get(&mut sys, &replicas[0], &key, 3, Some(expected), 100)?;
+    check_get(
+        &mut sys,
+        &replicas[0],
+        &key,
+        config.replication_factor,
+        Some(expected),
+        100,
+    )?;
     Ok(true)
 }
 
@@ -484,27 +679,43 @@ fn test_sloppy_quorum(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
 
-    // put key from the first non-replica with quorum 3
+    // put key from the first non-replica with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &non_replicas[0], &key, &value, None, 3, 100)?;
+    check_put(
+        &mut sys,
+        &non_replicas[0],
+        &key,
+        &value,
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // temporarily disconnect the first replica
     sys.disconnect_node(&replicas[0]);
 
-    // update key from the second non-replica with quorum 3 (should use sloppy quorum)
+    // update key from the second non-replica with quorum N (should use sloppy quorum)
     let (_, ctx) = check_get(&mut sys, &non_replicas[1], &key, 1, Some(vec![&value]), 100)?;
     let value2 = random_string(8, &mut rand);
-    check_put(&mut sys, &non_replicas[1], &key, &value2, ctx, 3, 100)?;
+    check_put(
+        &mut sys,
+        &non_replicas[1],
+        &key,
+        &value2,
+        ctx,
+        config.replication_factor,
+        100,
+    )?;
 
-    // read key from the last non-replica with quorum 3 (should use sloppy quorum)
+    // read key from the last non-replica with quorum N (should use sloppy quorum)
     check_get(
         &mut sys,
         &non_replicas[2],
         &key,
-        3,
+        config.replication_factor,
         Some(vec![&value2]),
         100,
     )?;
@@ -525,38 +736,101 @@ fn test_partitioned_clients(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = random_string(8, &mut rand).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let replica1 = &replicas[0];
-    let replica2 = &replicas[1];
-    let replica3 = &replicas[2];
-    let non_replicas = key_non_replicas(&key, &sys);
+    let replicas = key_replicas(&key, config);
+    let non_replicas = key_non_replicas(&key, config);
     let non_replica1 = &non_replicas[0];
     let non_replica2 = &non_replicas[1];
     let non_replica3 = &non_replicas[2];
 
-    // put key from the first node with quorum 3
+    // put key from the first node with quorum N
     let value = random_string(8, &mut rand);
-    check_put(&mut sys, &nodes[0], &key, &value, None, 3, 100)?;
+    check_put(
+        &mut sys,
+        &nodes[0],
+        &key,
+        &value,
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // partition nodes into two parts
-    let part1: Vec<&str> = vec![non_replica1, non_replica2, replica1];
-    let part2: Vec<&str> = vec![non_replica3, replica2, replica3];
+    let (part1, part2) = mixed_partition(&replicas, &non_replicas);
     sys.make_partition(&part1, &part2);
 
     // partition 1
-    let (values, ctx) = check_get(&mut sys, non_replica1, &key, 2, Some(vec![&value]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        non_replica1,
+        &key,
+        config.read_quorum,
+        Some(vec![&value]),
+        100,
+    )?;
     let mut value2 = format!("{}-1", values[0]);
-    check_put(&mut sys, non_replica1, &key, &value2, ctx, 2, 100)?;
-    let (values, ctx) = check_get(&mut sys, non_replica2, &key, 2, Some(vec![&value2]), 100)?;
+    check_put(
+        &mut sys,
+        non_replica1,
+        &key,
+        &value2,
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        non_replica2,
+        &key,
+        config.read_quorum,
+        Some(vec![&value2]),
+        100,
+    )?;
     value2 = format!("{}-2", values[0]);
-    check_put(&mut sys, non_replica2, &key, &value2, ctx, 2, 100)?;
-    check_get(&mut sys, non_replica2, &key, 2, Some(vec![&value2]), 100)?;
+    check_put(
+        &mut sys,
+        non_replica2,
+        &key,
+        &value2,
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
+    check_get(
+        &mut sys,
+        non_replica2,
+        &key,
+        config.read_quorum,
+        Some(vec![&value2]),
+        100,
+    )?;
 
     // partition 2
-    let (values, ctx) = check_get(&mut sys, non_replica3, &key, 2, Some(vec![&value]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        non_replica3,
+        &key,
+        config.read_quorum,
+        Some(vec![&value]),
+        100,
+    )?;
     let value3 = format!("{}-3", values[0]);
-    check_put(&mut sys, non_replica3, &key, &value3, ctx, 2, 100)?;
-    check_get(&mut sys, non_replica3, &key, 2, Some(vec![&value3]), 100)?;
+    check_put(
+        &mut sys,
+        non_replica3,
+        &key,
+        &value3,
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
+    check_get(
+        &mut sys,
+        non_replica3,
+        &key,
+        config.read_quorum,
+        Some(vec![&value3]),
+        100,
+    )?;
 
     // heal partition
     sys.reset_network();
@@ -565,9 +839,30 @@ fn test_partitioned_clients(config: &TestConfig) -> TestResult {
     // read key from all non-replicas
     // (should return value2 and value3)
     let expected: Option<Vec<&str>> = Some(vec![&value2, &value3]);
-    check_get(&mut sys, non_replica1, &key, 2, expected.clone(), 100)?;
-    check_get(&mut sys, non_replica2, &key, 2, expected.clone(), 100)?;
-    check_get(&mut sys, non_replica3, &key, 2, expected.clone(), 100)?;
+    check_get(
+        &mut sys,
+        non_replica1,
+        &key,
+        config.read_quorum,
+        expected.clone(),
+        100,
+    )?;
+    check_get(
+        &mut sys,
+        non_replica2,
+        &key,
+        config.read_quorum,
+        expected.clone(),
+        100,
+    )?;
+    check_get(
+        &mut sys,
+        non_replica3,
+        &key,
+        config.read_quorum,
+        expected.clone(),
+        100,
+    )?;
 
     // check all replicas
     for replica in replicas.iter() {
@@ -582,36 +877,76 @@ fn test_shopping_cart_1(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = format!("cart-{}", random_string(8, &mut rand)).to_uppercase();
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
     let node1 = &non_replicas[0];
     let node2 = &non_replicas[1];
 
     // node1: + milk
     let mut cart1 = vec!["milk"];
-    let (values, ctx1) = check_put(&mut sys, node1, &key, &cart1.join(","), None, 2, 100)?;
+    let (values, ctx1) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart1.join(","),
+        None,
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
     cart1 = values[0].split(",").collect();
 
     // node2: + eggs
     let mut cart2 = vec!["eggs"];
-    let (values, ctx2) = check_put(&mut sys, node2, &key, &cart2.join(","), None, 2, 100)?;
+    let (values, ctx2) = check_put(
+        &mut sys,
+        node2,
+        &key,
+        &cart2.join(","),
+        None,
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
     cart2 = values[0].split(",").collect();
 
     // node1: + flour
     cart1.push("flour");
-    let (values, ctx1) = check_put(&mut sys, node1, &key, &cart1.join(","), Some(ctx1), 2, 100)?;
+    let (values, ctx1) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart1.join(","),
+        Some(ctx1),
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
     cart1 = values[0].split(",").collect();
 
     // node2: + ham
     cart2.push("ham");
-    let (values, _) = check_put(&mut sys, node2, &key, &cart2.join(","), Some(ctx2), 2, 100)?;
+    let (values, _) = check_put(
+        &mut sys,
+        node2,
+        &key,
+        &cart2.join(","),
+        Some(ctx2),
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
 
     // node1: + flour
     cart1.push("bacon");
-    let (values, _) = check_put(&mut sys, node1, &key, &cart1.join(","), Some(ctx1), 2, 100)?;
+    let (values, _) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart1.join(","),
+        Some(ctx1),
+        config.write_quorum,
+        100,
+    )?;
     assume_eq!(values.len(), 1, "Expected single value")?;
 
     // read cart from all non-replicas
@@ -619,7 +954,7 @@ fn test_shopping_cart_1(config: &TestConfig) -> TestResult {
         .into_iter()
         .collect();
     for node in non_replicas.iter() {
-        let (values, _) = check_get(&mut sys, node, &key, 2, None, 100)?;
+        let (values, _) = check_get(&mut sys, node, &key, config.read_quorum, None, 100)?;
         check_cart_values(&values, &expected)?;
     }
     Ok(true)
@@ -631,22 +966,27 @@ fn test_shopping_cart_2(config: &TestConfig) -> TestResult {
 
     let key = format!("cart-{}", random_string(8, &mut rand)).to_uppercase();
 
-    let replicas = key_replicas(&key, &sys);
-    let replica1 = &replicas[0];
+    let replicas = key_replicas(&key, config);
     let replica2 = &replicas[1];
-    let replica3 = &replicas[2];
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
     let node1 = &non_replicas[0];
     let node2 = &non_replicas[1];
     let node3 = &non_replicas[2];
 
     // node1: [beer, snacks]
     let cart0 = vec!["beer", "snacks"];
-    let (_, ctx) = check_put(&mut sys, node1, &key, &cart0.join(","), None, 3, 100)?;
+    let (_, ctx) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart0.join(","),
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // partition nodes into two parts
-    let part1: Vec<&str> = vec![node1, node2, replica1];
-    let part2: Vec<&str> = vec![node3, replica2, replica3];
+    let (part1, part2) = mixed_partition(&replicas, &non_replicas);
     sys.make_partition(&part1, &part2);
 
     // partition 1 -----------------------------------------------------------------------------------------------------
@@ -654,30 +994,75 @@ fn test_shopping_cart_2(config: &TestConfig) -> TestResult {
     // node1: + milk
     let mut cart1 = cart0.clone();
     cart1.push("milk");
-    check_put(&mut sys, node1, &key, &cart1.join(","), Some(ctx), 2, 100)?;
+    check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart1.join(","),
+        Some(ctx),
+        config.write_quorum,
+        100,
+    )?;
     // node2: read, + eggs
-    let (values, ctx) = check_get(&mut sys, node2, &key, 2, Some(vec![&cart1.join(",")]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        node2,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart1.join(",")]),
+        100,
+    )?;
     let mut cart2: Vec<_> = values[0].split(",").collect();
     cart2.push("eggs");
-    check_put(&mut sys, node2, &key, &cart2.join(","), ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        node2,
+        &key,
+        &cart2.join(","),
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     // control read
-    check_get(&mut sys, node1, &key, 2, Some(vec![&cart2.join(",")]), 100)?;
+    check_get(
+        &mut sys,
+        node1,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart2.join(",")]),
+        100,
+    )?;
 
     // partition 2 -----------------------------------------------------------------------------------------------------
 
     // node3: read, remove [snacks, beer], + [cheese, wine]
-    let (values, ctx) = check_get(&mut sys, node3, &key, 2, Some(vec![&cart0.join(",")]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        node3,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart0.join(",")]),
+        100,
+    )?;
     let mut cart3: Vec<_> = values[0].split(",").collect();
     cart3.clear();
     cart3.push("cheese");
     cart3.push("wine");
-    check_put(&mut sys, node3, &key, &cart3.join(","), ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        node3,
+        &key,
+        &cart3.join(","),
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     // control read
     check_get(
         &mut sys,
         replica2,
         &key,
-        2,
+        config.read_quorum,
         Some(vec![&cart3.join(",")]),
         100,
     )?;
@@ -691,7 +1076,7 @@ fn test_shopping_cart_2(config: &TestConfig) -> TestResult {
         .into_iter()
         .collect();
     for node in non_replicas.iter() {
-        let (values, _) = check_get(&mut sys, node, &key, 2, None, 100)?;
+        let (values, _) = check_get(&mut sys, node, &key, config.read_quorum, None, 100)?;
         check_cart_values(&values, &expected)?;
     }
 
@@ -709,22 +1094,27 @@ fn test_shopping_xcart_1(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = format!("xcart-{}", random_string(8, &mut rand)).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let replica1 = &replicas[0];
+    let replicas = key_replicas(&key, config);
     let replica2 = &replicas[1];
-    let replica3 = &replicas[2];
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
     let node1 = &non_replicas[0];
     let node2 = &non_replicas[1];
     let node3 = &non_replicas[2];
 
     // node1: [beer, snacks]
     let cart0 = vec!["beer", "snacks"];
-    let (_, ctx) = check_put(&mut sys, node1, &key, &cart0.join(","), None, 3, 100)?;
+    let (_, ctx) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart0.join(","),
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // partition nodes into two parts
-    let part1: Vec<&str> = vec![node1, node2, replica1];
-    let part2: Vec<&str> = vec![node3, replica2, replica3];
+    let (part1, part2) = mixed_partition(&replicas, &non_replicas);
     sys.make_partition(&part1, &part2);
 
     // partition 1 -----------------------------------------------------------------------------------------------------
@@ -732,30 +1122,75 @@ fn test_shopping_xcart_1(config: &TestConfig) -> TestResult {
     // node1: + milk
     let mut cart1 = cart0.clone();
     cart1.push("milk");
-    check_put(&mut sys, node1, &key, &cart1.join(","), Some(ctx), 2, 100)?;
+    check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart1.join(","),
+        Some(ctx),
+        config.write_quorum,
+        100,
+    )?;
     // node2: read, + eggs
-    let (values, ctx) = check_get(&mut sys, node2, &key, 2, Some(vec![&cart1.join(",")]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        node2,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart1.join(",")]),
+        100,
+    )?;
     let mut cart2: Vec<_> = values[0].split(",").collect();
     cart2.push("eggs");
-    check_put(&mut sys, node2, &key, &cart2.join(","), ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        node2,
+        &key,
+        &cart2.join(","),
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     // control read
-    check_get(&mut sys, node1, &key, 2, Some(vec![&cart2.join(",")]), 100)?;
+    check_get(
+        &mut sys,
+        node1,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart2.join(",")]),
+        100,
+    )?;
 
     // partition 2 -----------------------------------------------------------------------------------------------------
 
     // node3: read, remove [snacks, beer], + [cheese, wine]
-    let (values, ctx) = check_get(&mut sys, node3, &key, 2, Some(vec![&cart0.join(",")]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        node3,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart0.join(",")]),
+        100,
+    )?;
     let mut cart3: Vec<_> = values[0].split(",").collect();
     cart3.clear();
     cart3.push("cheese");
     cart3.push("wine");
-    check_put(&mut sys, node3, &key, &cart3.join(","), ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        node3,
+        &key,
+        &cart3.join(","),
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     // control read
     check_get(
         &mut sys,
         replica2,
         &key,
-        2,
+        config.read_quorum,
         Some(vec![&cart3.join(",")]),
         100,
     )?;
@@ -767,7 +1202,7 @@ fn test_shopping_xcart_1(config: &TestConfig) -> TestResult {
     // read key from all non-replica nodes
     let expected: HashSet<_> = vec!["cheese", "wine", "milk", "eggs"].into_iter().collect();
     for node in non_replicas.iter() {
-        let (values, _) = check_get(&mut sys, node, &key, 2, None, 100)?;
+        let (values, _) = check_get(&mut sys, node, &key, config.read_quorum, None, 100)?;
         check_cart_values(&values, &expected)?;
     }
 
@@ -785,22 +1220,27 @@ fn test_shopping_xcart_2(config: &TestConfig) -> TestResult {
     let mut rand = Pcg64::seed_from_u64(config.seed);
 
     let key = format!("xcart-{}", random_string(8, &mut rand)).to_uppercase();
-    let replicas = key_replicas(&key, &sys);
-    let replica1 = &replicas[0];
+    let replicas = key_replicas(&key, config);
     let replica2 = &replicas[1];
-    let replica3 = &replicas[2];
-    let non_replicas = key_non_replicas(&key, &sys);
+    let non_replicas = key_non_replicas(&key, config);
     let node1 = &non_replicas[0];
     let node2 = &non_replicas[1];
     let node3 = &non_replicas[2];
 
     // node1: [lemonade, snacks, beer]
     let cart0 = vec!["lemonade", "snacks", "beer"];
-    let (_, ctx) = check_put(&mut sys, node1, &key, &cart0.join(","), None, 3, 100)?;
+    let (_, ctx) = check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart0.join(","),
+        None,
+        config.replication_factor,
+        100,
+    )?;
 
     // partition nodes into two parts
-    let part1: Vec<&str> = vec![node1, node2, replica1];
-    let part2: Vec<&str> = vec![node3, replica2, replica3];
+    let (part1, part2) = mixed_partition(&replicas, &non_replicas);
     sys.make_partition(&part1, &part2);
 
     // partition 1 -----------------------------------------------------------------------------------------------------
@@ -809,33 +1249,86 @@ fn test_shopping_xcart_2(config: &TestConfig) -> TestResult {
     let mut cart1 = cart0.clone();
     cart1.remove(0);
     cart1.push("milk");
-    check_put(&mut sys, node1, &key, &cart1.join(","), Some(ctx), 2, 100)?;
+    check_put(
+        &mut sys,
+        node1,
+        &key,
+        &cart1.join(","),
+        Some(ctx),
+        config.write_quorum,
+        100,
+    )?;
     // node2: read, + eggs
-    let (values, ctx) = check_get(&mut sys, node2, &key, 2, Some(vec![&cart1.join(",")]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        node2,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart1.join(",")]),
+        100,
+    )?;
     let mut cart2: Vec<_> = values[0].split(",").collect();
     cart2.push("eggs");
-    check_put(&mut sys, node2, &key, &cart2.join(","), ctx, 2, 100)?;
+    check_put(
+        &mut sys,
+        node2,
+        &key,
+        &cart2.join(","),
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     // control read
-    check_get(&mut sys, node1, &key, 2, Some(vec![&cart2.join(",")]), 100)?;
+    check_get(
+        &mut sys,
+        node1,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart2.join(",")]),
+        100,
+    )?;
 
     // partition 2 -----------------------------------------------------------------------------------------------------
 
     // node3: read, remove [snacks, beer], + [cheese, wine], + snacks (back)
-    let (values, ctx) = check_get(&mut sys, node3, &key, 2, Some(vec![&cart0.join(",")]), 100)?;
+    let (values, ctx) = check_get(
+        &mut sys,
+        node3,
+        &key,
+        config.read_quorum,
+        Some(vec![&cart0.join(",")]),
+        100,
+    )?;
     let mut cart3: Vec<_> = values[0].split(",").collect();
     cart3.clear();
     cart3.push("lemonade");
     cart3.push("cheese");
     cart3.push("wine");
-    let (_, ctx) = check_put(&mut sys, node3, &key, &cart3.join(","), ctx, 2, 100)?;
+    let (_, ctx) = check_put(
+        &mut sys,
+        node3,
+        &key,
+        &cart3.join(","),
+        ctx,
+        config.write_quorum,
+        100,
+    )?;
     cart3.push("snacks");
-    check_put(&mut sys, node3, &key, &cart3.join(","), Some(ctx), 2, 100)?;
+    check_put(
+        &mut sys,
+        node3,
+        &key,
+        &cart3.join(","),
+        Some(ctx),
+        config.write_quorum,
+        100,
+    )?;
     // control read
     check_get(
         &mut sys,
         replica2,
         &key,
-        2,
+        config.read_quorum,
         Some(vec![&cart3.join(",")]),
         100,
     )?;
@@ -849,7 +1342,7 @@ fn test_shopping_xcart_2(config: &TestConfig) -> TestResult {
         .into_iter()
         .collect();
     for node in non_replicas.iter() {
-        let (values, _) = check_get(&mut sys, node, &key, 2, None, 100)?;
+        let (values, _) = check_get(&mut sys, node, &key, config.read_quorum, None, 100)?;
         check_cart_values(&values, &expected)?;
     }
 
@@ -888,6 +1381,22 @@ struct Args {
     #[clap(long, short, default_value = "123")]
     seed: u64,
 
+    /// Replication factor (N)
+    #[clap(long, default_value = "3")]
+    replicas: u8,
+
+    /// Read quorum used in tests instead of 2 (R, majority of replicas by default)
+    #[clap(long)]
+    read_quorum: Option<u8>,
+
+    /// Write quorum used in tests instead of 2 (W, majority of replicas by default)
+    #[clap(long)]
+    write_quorum: Option<u8>,
+
+    /// Number of virtual nodes per node for ring placement (consecutive nodes are used if 0)
+    #[clap(long, default_value = "0")]
+    vnodes: u32,
+
     /// Path to dslib directory
     #[clap(long = "lib", short = 'l', default_value = "../../dslib")]
     dslib_path: String,
@@ -895,6 +1404,11 @@ struct Args {
 
 // MAIN --------------------------------------------------------------------------------------------
 
+fn usage_error(msg: &str) -> ! {
+    eprintln!("error: {}", msg);
+    std::process::exit(2);
+}
+
 fn main() {
     let args = Args::parse();
     if args.debug {
@@ -903,12 +1417,36 @@ fn main() {
     env::set_var("PYTHONPATH", format!("{}/python", args.dslib_path));
     env::set_var("PYTHONHASHSEED", args.seed.to_string());
     let node_factory = PyNodeFactory::new(&args.solution_path, "StorageNode");
+    let majority = args.replicas / 2 + 1;
     let config = TestConfig {
         node_factory: &node_factory,
         node_count: args.node_count,
         seed: args.seed,
+        replication_factor: args.replicas,
+        read_quorum: args.read_quorum.unwrap_or(majority),
+        write_quorum: args.write_quorum.unwrap_or(majority),
+        vnodes: args.vnodes,
     };
 
+    if config.replication_factor < 3 {
+        usage_error("tests require at least 3 replicas, use --replicas 3 or more");
+    }
+    if config.node_count < config.replication_factor as u32 + 3 {
+        usage_error(&format!(
+            "tests require at least 3 non-replicas, use -n {} or more with {} replicas",
+            config.replication_factor as u32 + 3,
+            config.replication_factor
+        ));
+    }
+    if !(config.read_quorum >= 1
+        && config.write_quorum >= 1
+        && config.read_quorum <= config.replication_factor
+        && config.write_quorum <= config.replication_factor
+        && config.read_quorum + config.write_quorum > config.replication_factor)
+    {
+        usage_error("tests require 1 <= R, W <= N and R + W > N");
+    }
+
     let mut tests = TestSuite::new();
     tests.add("BASIC", test_basic, config);
     tests.add("STALE REPLICA", test_stale_replica, config);